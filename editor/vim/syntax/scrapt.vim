@@ -2,10 +2,10 @@ if exists("b:current_syntax")
 	finish
 end
 
-syn keyword scraptKeyword set vars lists broadcasts costumes sounds def when
+syn keyword scraptKeyword set sprite vars lists broadcasts costumes sounds cloud const import def when
 syn keyword scraptMediaTypes SVG PNG WAV MP4
-syn keyword scraptConditional if else
-syn keyword scraptRepeat repeat
+syn keyword scraptConditional if else match
+syn keyword scraptRepeat repeat until while forever for in
 syn keyword scraptBoolean true false
 syn match scraptNumber "-\=\<[0-9]*\>"
 syn match scraptFloat "-\=\<[0-9]*\.[0-9]*\>"
@@ -0,0 +1,102 @@
+//! Reading and writing whole `.sb3` files, which are ZIP archives of a `project.json` and the assets
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, Write};
+use std::path::Path;
+
+use thiserror::Error;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::Project;
+
+const PROJECT_JSON: &str = "project.json";
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid ZIP archive")]
+    Zip(#[from] ZipError),
+
+    #[error("The archive contains no project.json")]
+    MissingProject,
+
+    #[error("Invalid project.json")]
+    Project(#[from] crate::Error),
+}
+
+/// A project together with the contents of its assets
+#[derive(Debug)]
+pub struct Sb3Archive {
+    pub project: Project,
+
+    /// The bytes of all assets, keyed by their file name in the archive, the `md5ext` of the asset
+    pub assets: BTreeMap<String, Vec<u8>>,
+}
+
+impl Sb3Archive {
+    pub fn new(project: Project) -> Sb3Archive {
+        Sb3Archive {
+            project,
+            assets: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_asset(&mut self, md5ext: String, bytes: Vec<u8>) {
+        self.assets.insert(md5ext, bytes);
+    }
+
+    /// Read the `.sb3` file at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Sb3Archive, ArchiveError> {
+        Sb3Archive::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read(reader: impl Read + Seek) -> Result<Sb3Archive, ArchiveError> {
+        let mut zip = ZipArchive::new(reader)?;
+
+        let mut project = None;
+        let mut assets = BTreeMap::new();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+
+            let name = file.name().to_string();
+            if name == PROJECT_JSON {
+                let mut json = String::new();
+                file.read_to_string(&mut json)?;
+                project = Some(Project::parse(&json)?);
+            } else {
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes)?;
+                assets.insert(name, bytes);
+            }
+        }
+
+        Ok(Sb3Archive {
+            project: project.ok_or(ArchiveError::MissingProject)?,
+            assets,
+        })
+    }
+
+    pub fn write(&self, writer: impl Write + Seek) -> Result<(), ArchiveError> {
+        let mut zip = ZipWriter::new(writer);
+        let options = SimpleFileOptions::default();
+
+        zip.start_file(PROJECT_JSON, options)?;
+        zip.write_all(self.project.to_json().as_bytes())?;
+
+        for (md5ext, bytes) in &self.assets {
+            zip.start_file(md5ext, options)?;
+            zip.write_all(bytes)?;
+        }
+
+        zip.finish()?;
+        Ok(())
+    }
+}
@@ -1,30 +1,206 @@
 use std::collections::HashMap;
 
-use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{Error, Expected, IgnoredAny, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::string_array::StringArray;
-use crate::{Id, IdOrPrimitiveBlock, Name, Number, Opcode, Value};
+use crate::{Extras, Id, IdOrPrimitiveBlock, Name, Number, Opcode, Value};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum Block {
     Primitive(PrimitiveBlock),
     Full(Box<FullBlock>),
 }
 
+impl<'de> Deserialize<'de> for Block {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct BlockVisitor;
+
+        impl<'de> Visitor<'de> for BlockVisitor {
+            type Value = Block;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a block object or a primitive block array")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Block, A::Error> {
+                PrimitiveVisitor.visit_seq(seq).map(Block::Primitive)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Block, A::Error> {
+                FullBlock::deserialize(MapAccessDeserializer::new(map))
+                    .map(|block| Block::Full(Box::new(block)))
+            }
+        }
+
+        // decide by the shape of the JSON without buffering it, so errors point at the invalid value
+        deserializer.deserialize_any(BlockVisitor)
+    }
+}
+
 impl Block {
     pub fn builder() -> builder::BlockBuilder {
         builder::BlockBuilder
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum PrimitiveBlock {
-    // TODO: investigate different numeral modes
-    Simple(u8, Value),
-    Advanced(u8, Name, Id),
-    AdvancedWithPos(u8, Name, Id, Number, Number),
+    Simple(ValueType, Value),
+    Advanced(ReferenceType, Name, Id),
+    AdvancedWithPos(ReferenceType, Name, Id, Number, Number),
+}
+
+impl<'de> Deserialize<'de> for PrimitiveBlock {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(PrimitiveVisitor)
+    }
+}
+
+pub(crate) struct PrimitiveVisitor;
+
+impl<'de> Visitor<'de> for PrimitiveVisitor {
+    type Value = PrimitiveBlock;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a primitive block of 2, 3 or 5 elements")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PrimitiveBlock, A::Error> {
+        // the type decides how many elements follow, so an invalid type is reported instead of "no variant matched"
+        let code: u8 = element(&mut seq, 0, &self)?;
+        if let Ok(ty) = ValueType::try_from(code) {
+            let block = PrimitiveBlock::Simple(ty, element(&mut seq, 1, &self)?);
+            end(&mut seq, 2, &self)?;
+            return Ok(block);
+        }
+
+        let ty = ReferenceType::try_from(code).map_err(|_| {
+            A::Error::invalid_value(
+                Unexpected::Unsigned(code.into()),
+                &"a primitive type from 4 to 13",
+            )
+        })?;
+        let name = element(&mut seq, 1, &self)?;
+        let id = element(&mut seq, 2, &self)?;
+        let Some(x) = seq.next_element()? else {
+            return Ok(PrimitiveBlock::Advanced(ty, name, id));
+        };
+        let y = element(&mut seq, 4, &self)?;
+        end(&mut seq, 5, &self)?;
+        Ok(PrimitiveBlock::AdvancedWithPos(ty, name, id, x, y))
+    }
+}
+
+/// The next element of a sequence, which has to be there
+fn element<'de, T, A>(seq: &mut A, index: usize, expected: &dyn Expected) -> Result<T, A::Error>
+where
+    T: Deserialize<'de>,
+    A: SeqAccess<'de>,
+{
+    seq.next_element()?
+        .ok_or_else(|| A::Error::invalid_length(index, expected))
+}
+
+/// Fail if a sequence has more than `len` elements
+fn end<'de, A: SeqAccess<'de>>(
+    seq: &mut A,
+    len: usize,
+    expected: &dyn Expected,
+) -> Result<(), A::Error> {
+    match seq.next_element::<IgnoredAny>()? {
+        Some(_) => Err(A::Error::invalid_length(len + 1, expected)),
+        None => Ok(()),
+    }
+}
+
+/// Defines a fieldless enum which is de/serialized as the number Scratch uses for it
+macro_rules! numeric_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident: $what:literal {
+            $($(#[$variant_meta:meta])* $variant:ident = $code:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+        #[serde(try_from = "u8", into = "u8")]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant = $code,)*
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = String;
+
+            fn try_from(code: u8) -> Result<Self, Self::Error> {
+                match code {
+                    $($code => Ok($name::$variant),)*
+                    _ => Err(format!(concat!("invalid ", $what, " {}"), code)),
+                }
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(value: $name) -> u8 {
+                value as u8
+            }
+        }
+    };
+}
+
+numeric_enum! {
+    /// The kind of slot a primitive block with a plain value fills
+    pub enum ValueType: "primitive value type" {
+        /// `math_number`
+        Number = 4,
+        /// `math_positive_number`
+        PositiveNumber = 5,
+        /// `math_whole_number`
+        PositiveInteger = 6,
+        /// `math_integer`
+        Integer = 7,
+        /// `math_angle`
+        Angle = 8,
+        /// `colour_picker`
+        Color = 9,
+        /// `text`
+        Text = 10,
+    }
+}
+
+numeric_enum! {
+    /// The kind of symbol a primitive block with a name and an ID refers to
+    pub enum ReferenceType: "primitive reference type" {
+        /// `event_broadcast_menu`
+        Broadcast = 11,
+        /// `data_variable`
+        Variable = 12,
+        /// `data_listcontents`
+        List = 13,
+    }
+}
+
+numeric_enum! {
+    /// How an input relates to its shadow block
+    pub enum ShadowType: "input shadow type" {
+        /// The input only holds its shadow block
+        Shadow = 1,
+        /// The input holds a block and has no shadow
+        NoShadow = 2,
+        /// The input holds a block obscuring its shadow block
+        ObscuredShadow = 3,
+    }
 }
 
 impl PrimitiveBlock {
@@ -53,13 +229,55 @@ pub struct FullBlock {
     pub comment: Option<Id>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mutation: Option<Mutation>,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum Input {
-    Simple(u8, IdOrPrimitiveBlock),
-    Obscuring(u8, IdOrPrimitiveBlock, IdOrPrimitiveBlock),
+    Simple(ShadowType, IdOrPrimitiveBlock),
+    Obscuring(ShadowType, IdOrPrimitiveBlock, IdOrPrimitiveBlock),
+}
+
+impl<'de> Deserialize<'de> for Input {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct InputVisitor;
+
+        impl<'de> Visitor<'de> for InputVisitor {
+            type Value = Input;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an input of 2 or 3 elements")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Input, A::Error> {
+                let ty = element(&mut seq, 0, &self)?;
+                let input = element(&mut seq, 1, &self)?;
+                let shadow = seq.next_element()?;
+                end(&mut seq, 3, &self)?;
+
+                match (ty, shadow) {
+                    (ShadowType::ObscuredShadow, Some(shadow)) => {
+                        Ok(Input::Obscuring(ty, input, shadow))
+                    }
+                    (ShadowType::ObscuredShadow, None) => Err(A::Error::custom(
+                        "input with an obscured shadow lacks the shadow",
+                    )),
+                    (_, Some(_)) => Err(A::Error::custom(
+                        "only inputs with an obscured shadow have two blocks",
+                    )),
+                    (_, None) => Ok(Input::Simple(ty, input)),
+                }
+            }
+        }
+
+        deserializer.deserialize_seq(InputVisitor)
+    }
 }
 
 impl Input {
@@ -80,27 +298,135 @@ impl CodePosition {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Mutation {
     pub tag_name: String,
-    pub children: [(); 0],
+    /// Child elements of the XML mutation, empty for all blocks of Scratch itself
+    pub children: Vec<serde_json::Value>,
 
     #[serde(flatten)]
     pub mutation_type: MutationType,
 }
 
+impl<'de> Deserialize<'de> for Mutation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(MutationVisitor)
+    }
+}
+
+struct MutationVisitor;
+
+impl<'de> Visitor<'de> for MutationVisitor {
+    type Value = Mutation;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a mutation object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Mutation, A::Error> {
+        // the keys decide the type of the mutation, but every value is deserialized right away,
+        // so errors inside of a known mutation point at the invalid value
+        let mut tag_name = None;
+        let mut children = None;
+        let mut proccode: Option<String> = None;
+        let mut argumentids: Option<StringArray<Id>> = None;
+        let mut warp: Option<Flag> = None;
+        let mut argumentnames: Option<StringArray<String>> = None;
+        let mut argumentdefaults: Option<StringArray<String>> = None;
+        let mut hasnext: Option<Flag> = None;
+        let mut extras = Extras::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "tagName" => tag_name = Some(map.next_value()?),
+                "children" => children = Some(map.next_value()?),
+                "proccode" => proccode = Some(map.next_value()?),
+                "argumentids" => argumentids = Some(map.next_value()?),
+                "warp" => warp = Some(map.next_value()?),
+                "argumentnames" => argumentnames = Some(map.next_value()?),
+                "argumentdefaults" => argumentdefaults = Some(map.next_value()?),
+                "hasnext" => hasnext = Some(map.next_value()?),
+                _ => {
+                    extras.insert(key, map.next_value()?);
+                }
+            }
+        }
+
+        let tag_name = tag_name.ok_or_else(|| A::Error::missing_field("tagName"))?;
+        let children = children.ok_or_else(|| A::Error::missing_field("children"))?;
+
+        let mutation_type = if let Some(proccode) = proccode {
+            keep(&mut extras, "hasnext", hasnext)?;
+            let prototype = match argumentnames {
+                Some(argumentnames) => Some(PrototypeMutation {
+                    argumentnames,
+                    // missing in projects of older Scratch versions
+                    argumentdefaults: argumentdefaults.unwrap_or_default(),
+                }),
+                None => {
+                    keep(&mut extras, "argumentdefaults", argumentdefaults)?;
+                    None
+                }
+            };
+
+            MutationType::Procedure(ProcedureMutation {
+                proccode,
+                argumentids: argumentids.ok_or_else(|| A::Error::missing_field("argumentids"))?,
+                warp: warp.ok_or_else(|| A::Error::missing_field("warp"))?.0,
+                prototype,
+                extras,
+            })
+        } else {
+            keep(&mut extras, "argumentids", argumentids)?;
+            keep(&mut extras, "warp", warp)?;
+            keep(&mut extras, "argumentnames", argumentnames)?;
+            keep(&mut extras, "argumentdefaults", argumentdefaults)?;
+
+            match hasnext {
+                Some(hasnext) => MutationType::ControlStop(ControlStopMutation {
+                    hasnext: hasnext.0,
+                    extras,
+                }),
+                None => MutationType::Other(extras),
+            }
+        };
+
+        Ok(Mutation {
+            tag_name,
+            children,
+            mutation_type,
+        })
+    }
+}
+
+/// Put a key that doesn't belong to the type of a mutation back into its extras
+fn keep<T: Serialize, E: Error>(extras: &mut Extras, key: &str, value: Option<T>) -> Result<(), E> {
+    if let Some(value) = value {
+        let value = serde_json::to_value(value).map_err(E::custom)?;
+        extras.insert(key.to_string(), value);
+    }
+    Ok(())
+}
+
 impl Mutation {
     pub fn builder() -> builder::MutationBuilder {
         builder::MutationBuilder
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum MutationType {
+    /// `procedures_call` and `procedures_prototype`
     Procedure(ProcedureMutation),
+    /// `control_stop`
     ControlStop(ControlStopMutation),
+    /// Mutations of extension blocks or from other editors
+    Other(Extras),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -108,28 +434,65 @@ pub enum MutationType {
 pub struct ProcedureMutation {
     pub proccode: String,
     pub argumentids: StringArray<Id>,
-
-    // TODO: maybe de/serialize it to/from bool
-    pub warp: String,
+    #[serde(with = "flag")]
+    pub warp: bool,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(flatten)]
     pub prototype: Option<PrototypeMutation>,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ControlStopMutation {
+    #[serde(with = "flag")]
     pub hasnext: bool,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PrototypeMutation {
     pub argumentnames: StringArray<String>,
+    // missing in projects of older Scratch versions
+    #[serde(default)]
     pub argumentdefaults: StringArray<String>,
 }
 
+/// A boolean attribute of a mutation on its own
+#[derive(Serialize, Deserialize)]
+struct Flag(#[serde(with = "flag")] bool);
+
+/// Scratch stores the boolean attributes of mutations as the strings `"true"` and `"false"`,
+/// but other editors also write real booleans
+mod flag {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(if *value { "true" } else { "false" })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Flag {
+            Bool(bool),
+            String(String),
+        }
+
+        // like Scratch, treat every string besides "true" as false
+        Ok(match Flag::deserialize(deserializer)? {
+            Flag::Bool(value) => value,
+            Flag::String(value) => value == "true",
+        })
+    }
+}
+
 pub mod builder {
     use super::*;
     use crate::Angle;
@@ -228,6 +591,7 @@ pub mod builder {
                 position: self.position,
                 comment: self.comment,
                 mutation: self.mutation,
+                extras: Extras::new(),
             }
         }
     }
@@ -236,49 +600,64 @@ pub mod builder {
 
     impl PrimitiveBlockBuilder {
         pub fn number(self, num: Number) -> PrimitiveBlock {
-            PrimitiveBlock::Simple(4, Value::Number(num))
+            PrimitiveBlock::Simple(ValueType::Number, Value::Number(num))
         }
 
-        // TODO
-        // pub fn positive_number(self, num: PositiveNumber)
+        /// `None` if `num` is negative or not a number
+        pub fn positive_number(self, num: Number) -> Option<PrimitiveBlock> {
+            let positive = match num {
+                Number::Integer(num) => num >= 0,
+                Number::Float(num) => num >= 0.0,
+            };
+            positive.then_some(PrimitiveBlock::Simple(
+                ValueType::PositiveNumber,
+                Value::Number(num),
+            ))
+        }
 
         pub fn positive_integer(self, num: u32) -> PrimitiveBlock {
-            PrimitiveBlock::Simple(6, Value::Number(Number::Integer(num as i32)))
+            PrimitiveBlock::Simple(
+                ValueType::PositiveInteger,
+                Value::Number(Number::Integer(num as i32)),
+            )
         }
 
         pub fn integer(self, num: i32) -> PrimitiveBlock {
-            PrimitiveBlock::Simple(7, Value::Number(Number::Integer(num)))
+            PrimitiveBlock::Simple(ValueType::Integer, Value::Number(Number::Integer(num)))
         }
 
         pub fn angle(self, angle: Angle) -> PrimitiveBlock {
-            PrimitiveBlock::Simple(8, Value::Number(Number::Integer(angle as i32)))
+            PrimitiveBlock::Simple(
+                ValueType::Angle,
+                Value::Number(Number::Integer(angle as i32)),
+            )
         }
 
         pub fn color(self, color: String) -> PrimitiveBlock {
-            PrimitiveBlock::Simple(9, Value::String(color))
+            PrimitiveBlock::Simple(ValueType::Color, Value::String(color))
         }
 
         pub fn string(self, string: String) -> PrimitiveBlock {
-            PrimitiveBlock::Simple(10, Value::String(string))
+            PrimitiveBlock::Simple(ValueType::Text, Value::String(string))
         }
 
         pub fn broadcast(self, name: Name, id: Id) -> PrimitiveBlock {
-            PrimitiveBlock::Advanced(11, name, id)
+            PrimitiveBlock::Advanced(ReferenceType::Broadcast, name, id)
         }
 
         pub fn variable(self, name: Name, id: Id, pos: Option<CodePosition>) -> PrimitiveBlock {
             if let Some(pos) = pos {
-                PrimitiveBlock::AdvancedWithPos(12, name, id, pos.x, pos.y)
+                PrimitiveBlock::AdvancedWithPos(ReferenceType::Variable, name, id, pos.x, pos.y)
             } else {
-                PrimitiveBlock::Advanced(12, name, id)
+                PrimitiveBlock::Advanced(ReferenceType::Variable, name, id)
             }
         }
 
         pub fn list(self, name: Name, id: Id, pos: Option<CodePosition>) -> PrimitiveBlock {
             if let Some(pos) = pos {
-                PrimitiveBlock::AdvancedWithPos(13, name, id, pos.x, pos.y)
+                PrimitiveBlock::AdvancedWithPos(ReferenceType::List, name, id, pos.x, pos.y)
             } else {
-                PrimitiveBlock::Advanced(13, name, id)
+                PrimitiveBlock::Advanced(ReferenceType::List, name, id)
             }
         }
     }
@@ -295,11 +674,11 @@ pub mod builder {
         }
 
         pub fn id(self, id: Id) -> Input {
-            Input::Simple(2, IdOrPrimitiveBlock::Id(id))
+            Input::Simple(ShadowType::NoShadow, IdOrPrimitiveBlock::Id(id))
         }
 
         pub fn primitive(self, block: PrimitiveBlock) -> Input {
-            Input::Simple(2, IdOrPrimitiveBlock::Primitive(block))
+            Input::Simple(ShadowType::NoShadow, IdOrPrimitiveBlock::Primitive(block))
         }
     }
 
@@ -307,11 +686,11 @@ pub mod builder {
 
     impl ShadowInputBuilder {
         pub fn id(self, id: Id) -> Input {
-            Input::Simple(1, IdOrPrimitiveBlock::Id(id))
+            Input::Simple(ShadowType::Shadow, IdOrPrimitiveBlock::Id(id))
         }
 
         pub fn primitive(self, block: PrimitiveBlock) -> Input {
-            Input::Simple(1, IdOrPrimitiveBlock::Primitive(block))
+            Input::Simple(ShadowType::Shadow, IdOrPrimitiveBlock::Primitive(block))
         }
     }
 
@@ -337,11 +716,19 @@ pub mod builder {
         }
 
         pub fn shadow_id(self, id: Id) -> Input {
-            Input::Obscuring(3, self.input, IdOrPrimitiveBlock::Id(id))
+            Input::Obscuring(
+                ShadowType::ObscuredShadow,
+                self.input,
+                IdOrPrimitiveBlock::Id(id),
+            )
         }
 
         pub fn shadow_primitve(self, block: PrimitiveBlock) -> Input {
-            Input::Obscuring(3, self.input, IdOrPrimitiveBlock::Primitive(block))
+            Input::Obscuring(
+                ShadowType::ObscuredShadow,
+                self.input,
+                IdOrPrimitiveBlock::Primitive(block),
+            )
         }
     }
 
@@ -363,8 +750,11 @@ pub mod builder {
         pub fn control_stop(self, hasnext: bool) -> Mutation {
             Mutation {
                 tag_name: String::from("mutation"),
-                children: [],
-                mutation_type: MutationType::ControlStop(ControlStopMutation { hasnext }),
+                children: Vec::new(),
+                mutation_type: MutationType::ControlStop(ControlStopMutation {
+                    hasnext,
+                    extras: Extras::new(),
+                }),
             }
         }
     }
@@ -372,7 +762,7 @@ pub mod builder {
     pub struct ProcedureCallMutationBuilder {
         proccode: String,
         argumentids: StringArray<Id>,
-        warp: String,
+        warp: bool,
     }
 
     impl ProcedureCallMutationBuilder {
@@ -380,7 +770,7 @@ pub mod builder {
             ProcedureCallMutationBuilder {
                 proccode: String::from(name),
                 argumentids: StringArray::new(),
-                warp: String::from("false"),
+                warp: false,
             }
         }
 
@@ -402,7 +792,7 @@ pub mod builder {
             self
         }
 
-        pub fn warp(mut self, warp: String) -> ProcedureCallMutationBuilder {
+        pub fn warp(mut self, warp: bool) -> ProcedureCallMutationBuilder {
             self.warp = warp;
             self
         }
@@ -410,12 +800,13 @@ pub mod builder {
         pub fn build(self) -> Mutation {
             Mutation {
                 tag_name: String::from("mutation"),
-                children: [],
+                children: Vec::new(),
                 mutation_type: MutationType::Procedure(ProcedureMutation {
                     proccode: self.proccode,
                     argumentids: self.argumentids,
                     warp: self.warp,
                     prototype: None,
+                    extras: Extras::new(),
                 }),
             }
         }
@@ -424,7 +815,7 @@ pub mod builder {
     pub struct ProcedurePrototypeMutationBuilder {
         proccode: String,
         argumentids: StringArray<Id>,
-        warp: String,
+        warp: bool,
         argumentnames: StringArray<String>,
         argumentdefaults: StringArray<String>,
     }
@@ -434,7 +825,7 @@ pub mod builder {
             ProcedurePrototypeMutationBuilder {
                 proccode: String::from(name),
                 argumentids: StringArray::new(),
-                warp: String::from("false"),
+                warp: false,
                 argumentnames: StringArray::new(),
                 argumentdefaults: StringArray::new(),
             }
@@ -472,7 +863,7 @@ pub mod builder {
             self
         }
 
-        pub fn warp(mut self, warp: String) -> ProcedurePrototypeMutationBuilder {
+        pub fn warp(mut self, warp: bool) -> ProcedurePrototypeMutationBuilder {
             self.warp = warp;
             self
         }
@@ -480,7 +871,7 @@ pub mod builder {
         pub fn build(self) -> Mutation {
             Mutation {
                 tag_name: String::from("mutation"),
-                children: [],
+                children: Vec::new(),
                 mutation_type: MutationType::Procedure(ProcedureMutation {
                     proccode: self.proccode,
                     argumentids: self.argumentids,
@@ -489,6 +880,7 @@ pub mod builder {
                         argumentnames: self.argumentnames,
                         argumentdefaults: self.argumentdefaults,
                     }),
+                    extras: Extras::new(),
                 }),
             }
         }
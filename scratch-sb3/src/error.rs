@@ -0,0 +1,137 @@
+use std::cell::Cell;
+
+use serde_json::error::Category;
+use thiserror::Error;
+
+use crate::id::IdError;
+use crate::string_array::StringArrayError;
+
+/// Why a `project.json` couldn't be parsed
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid JSON at line {line}, column {column}: {message}")]
+    Syntax {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+
+    #[error("Unexpected value at `{path}` (line {line}, column {column}): {message}")]
+    Schema {
+        /// Where the value is in the project, e.g. `targets[1].blocks.abc.inputs`
+        path: String,
+        message: String,
+        line: usize,
+        column: usize,
+    },
+
+    /// Only [`Project::parse`](crate::Project::parse) keeps the typed cause, elsewhere these are [`Error::Schema`]s
+    #[error("Invalid value at `{path}` (line {line}, column {column}): {source}")]
+    Data {
+        path: String,
+        source: DataError,
+        line: usize,
+        column: usize,
+    },
+}
+
+/// A value of the right JSON type that still isn't valid
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DataError {
+    #[error(transparent)]
+    Id(#[from] IdError),
+
+    #[error(transparent)]
+    StringArray(#[from] StringArrayError),
+}
+
+thread_local! {
+    /// Whether [`Project::parse`](crate::Project::parse) is running, only then [`DATA_ERROR`] is used
+    static IN_PARSE: Cell<bool> = const { Cell::new(false) };
+
+    /// The cause of the last error made by [`data_error`], as serde_json only keeps the message
+    static DATA_ERROR: Cell<Option<DataError>> = const { Cell::new(None) };
+}
+
+/// A deserialization error which [`Error`] turns back into the typed `err`
+///
+/// The cause is only kept while [`Project::parse`](crate::Project::parse) runs, other errors just carry the message.
+pub(crate) fn data_error<E: serde::de::Error>(err: impl Into<DataError>) -> E {
+    let err = err.into();
+    let message = err.to_string();
+    if IN_PARSE.get() {
+        DATA_ERROR.set(Some(err));
+    }
+    E::custom(message)
+}
+
+/// Keeps the causes of [`data_error`]s for as long as it lives
+///
+/// Nothing is left over for later parses, no matter how parsing ends.
+pub(crate) struct ParseScope(());
+
+impl ParseScope {
+    pub(crate) fn enter() -> ParseScope {
+        IN_PARSE.set(true);
+        DATA_ERROR.take();
+        ParseScope(())
+    }
+}
+
+impl Drop for ParseScope {
+    fn drop(&mut self) {
+        IN_PARSE.set(false);
+        DATA_ERROR.take();
+    }
+}
+
+impl Error {
+    /// Sort a serde_json error, `path` leads to the value it is about
+    fn from_json(path: String, err: serde_json::Error) -> Error {
+        let (line, column) = (err.line(), err.column());
+
+        // serde_json appends the position to the message
+        let message = err.to_string();
+        let message = message
+            .strip_suffix(&format!(" at line {line} column {column}"))
+            .unwrap_or(&message)
+            .to_string();
+
+        let cause = DATA_ERROR.take();
+        match err.classify() {
+            Category::Io | Category::Syntax | Category::Eof => Error::Syntax {
+                message,
+                line,
+                column,
+            },
+            Category::Data => match cause {
+                // untagged enums recover from errors, so the stored one may be from an earlier attempt
+                Some(source) if source.to_string() == message => Error::Data {
+                    path,
+                    source,
+                    line,
+                    column,
+                },
+                _ => Error::Schema {
+                    path,
+                    message,
+                    line,
+                    column,
+                },
+            },
+        }
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for Error {
+    fn from(err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let path = err.path().to_string();
+        Error::from_json(path, err.into_inner())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::from_json(String::from("."), err)
+    }
+}
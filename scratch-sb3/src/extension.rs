@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum Extension {
     Pen,
     Wedo2,
@@ -14,3 +15,24 @@ pub enum Extension {
     Boost,
     Gdxfor,
 }
+
+impl Extension {
+    /// The extension a block belongs to, judging by the prefix of its opcode
+    pub fn of_opcode(opcode: &str) -> Option<Extension> {
+        let (prefix, _) = opcode.split_once('_')?;
+        Some(match prefix {
+            "pen" => Extension::Pen,
+            "wedo2" => Extension::Wedo2,
+            "music" => Extension::Music,
+            "microbit" => Extension::Microbit,
+            "text2speech" => Extension::Text2speech,
+            "translate" => Extension::Translate,
+            "videoSensing" => Extension::VideoSensing,
+            "ev3" => Extension::Ev3,
+            "makeymakey" => Extension::Makeymakey,
+            "boost" => Extension::Boost,
+            "gdxfor" => Extension::Gdxfor,
+            _ => return None,
+        })
+    }
+}
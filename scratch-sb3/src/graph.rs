@@ -0,0 +1,278 @@
+//! A view of the blocks of a target as scripts, stacks and inputs instead of a flat map
+
+use std::collections::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::block::{Block, FullBlock, Input, PrimitiveBlock, ShadowType};
+use crate::{Id, IdOrPrimitiveBlock, Name};
+
+/// The blocks of a target, see [`Target::graph`](crate::target::Target::graph)
+#[derive(Debug, Clone, Copy)]
+pub struct BlockGraph<'a> {
+    blocks: &'a HashMap<Id, Block>,
+}
+
+impl<'a> BlockGraph<'a> {
+    pub fn new(blocks: &'a HashMap<Id, Block>) -> BlockGraph<'a> {
+        BlockGraph { blocks }
+    }
+
+    /// The block with the given ID, if it is a full block
+    pub fn get(&self, id: Id) -> Option<BlockRef<'a>> {
+        match self.blocks.get(&id)? {
+            Block::Full(block) => Some(BlockRef {
+                graph: *self,
+                id,
+                block,
+            }),
+            Block::Primitive(_) => None,
+        }
+    }
+
+    /// The first blocks of all scripts, from top to bottom and left to right on the canvas
+    ///
+    /// Loose reporters of variables and lists aren't included, as they aren't full blocks
+    pub fn scripts(&self) -> Vec<BlockRef<'a>> {
+        let mut scripts: Vec<_> = self
+            .blocks
+            .keys()
+            .filter_map(|&id| self.get(id))
+            .filter(|block| block.block.top_level)
+            .collect();
+
+        scripts.sort_by(|a, b| {
+            let position = |block: &BlockRef| {
+                block
+                    .block
+                    .position
+                    .as_ref()
+                    .map(|pos| (f32::from(pos.y), f32::from(pos.x)))
+                    .unwrap_or((f32::INFINITY, f32::INFINITY))
+            };
+            let ((a_y, a_x), (b_y, b_x)) = (position(a), position(b));
+            a_y.total_cmp(&b_y)
+                .then(a_x.total_cmp(&b_x))
+                .then_with(|| a.id.to_string().cmp(&b.id.to_string()))
+        });
+        scripts
+    }
+
+    /// Check that all references between blocks lead to existing blocks which point back,
+    /// and that every block is part of a script
+    pub fn validate(&self) -> Vec<GraphError> {
+        let mut errors = Vec::new();
+
+        for (&id, block) in self.blocks {
+            let Block::Full(block) = block else {
+                continue;
+            };
+
+            if let Some(next) = block.next {
+                match self.get(next) {
+                    None => errors.push(GraphError::DanglingNext { block: id, next }),
+                    Some(next) if next.block.parent != Some(id) => {
+                        errors.push(GraphError::WrongParent {
+                            block: next.id,
+                            expected: id,
+                        })
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if let Some(parent) = block.parent
+                && self.get(parent).is_none()
+            {
+                errors.push(GraphError::DanglingParent { block: id, parent });
+            }
+
+            for (name, input) in &block.inputs {
+                for child in input_ids(input) {
+                    match self.blocks.get(&child) {
+                        None => errors.push(GraphError::DanglingInput {
+                            block: id,
+                            input: name.clone(),
+                            child,
+                        }),
+                        Some(Block::Full(child_block)) if child_block.parent != Some(id) => errors
+                            .push(GraphError::WrongParent {
+                                block: child,
+                                expected: id,
+                            }),
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+
+        // walk up from every block to the top of its script
+        let mut checked = HashSet::new();
+        for &id in self.blocks.keys() {
+            let mut path = Vec::new();
+            let mut current = self.get(id);
+            while let Some(block) = current {
+                if checked.contains(&block.id) {
+                    break;
+                }
+                if path.contains(&block.id) {
+                    errors.push(GraphError::Cycle { block: block.id });
+                    break;
+                }
+                path.push(block.id);
+
+                match block.block.parent {
+                    Some(parent) => current = self.get(parent),
+                    None => {
+                        if !block.block.top_level {
+                            errors.push(GraphError::Orphan { block: block.id });
+                        }
+                        break;
+                    }
+                }
+            }
+            checked.extend(path);
+        }
+
+        errors
+    }
+}
+
+/// A full block together with the graph it is part of
+#[derive(Debug, Clone, Copy)]
+pub struct BlockRef<'a> {
+    graph: BlockGraph<'a>,
+    id: Id,
+    block: &'a FullBlock,
+}
+
+impl<'a> BlockRef<'a> {
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    pub fn block(&self) -> &'a FullBlock {
+        self.block
+    }
+
+    pub fn next(&self) -> Option<BlockRef<'a>> {
+        self.graph.get(self.block.next?)
+    }
+
+    pub fn parent(&self) -> Option<BlockRef<'a>> {
+        self.graph.get(self.block.parent?)
+    }
+
+    /// This block and the ones following it
+    pub fn stack(&self) -> Stack<'a> {
+        Stack {
+            current: Some(*self),
+            remaining: self.graph.blocks.len(),
+        }
+    }
+
+    /// What fills the input, which is the obscuring block if there is one
+    pub fn input(&self, name: &str) -> Option<InputRef<'a>> {
+        match self.block.inputs.get(name)? {
+            Input::Simple(_, input) | Input::Obscuring(_, input, _) => self.resolve(input),
+        }
+    }
+
+    /// The shadow block of the input, if it has one
+    pub fn shadow(&self, name: &str) -> Option<InputRef<'a>> {
+        match self.block.inputs.get(name)? {
+            Input::Simple(ShadowType::Shadow, shadow) | Input::Obscuring(_, _, shadow) => {
+                self.resolve(shadow)
+            }
+            Input::Simple(_, _) => None,
+        }
+    }
+
+    /// The stack inside of a C block's input like `SUBSTACK`, empty if there is none
+    pub fn substack(&self, name: &str) -> Stack<'a> {
+        match self.input(name) {
+            Some(InputRef::Block(first)) => first.stack(),
+            _ => Stack {
+                current: None,
+                remaining: 0,
+            },
+        }
+    }
+
+    fn resolve(&self, input: &'a IdOrPrimitiveBlock) -> Option<InputRef<'a>> {
+        match input {
+            IdOrPrimitiveBlock::Id(id) => match self.graph.blocks.get(id)? {
+                Block::Full(_) => self.graph.get(*id).map(InputRef::Block),
+                Block::Primitive(primitive) => Some(InputRef::Primitive(primitive)),
+            },
+            IdOrPrimitiveBlock::Primitive(primitive) => Some(InputRef::Primitive(primitive)),
+        }
+    }
+}
+
+/// The content of an input
+#[derive(Debug, Clone, Copy)]
+pub enum InputRef<'a> {
+    Block(BlockRef<'a>),
+    Primitive(&'a PrimitiveBlock),
+}
+
+/// Iterator over a block and the ones following it
+///
+/// It ends after as many blocks as the graph has, so cycles don't make it loop forever.
+#[derive(Debug, Clone)]
+pub struct Stack<'a> {
+    current: Option<BlockRef<'a>>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Stack<'a> {
+    type Item = BlockRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let current = self.current.take()?;
+        self.current = current.next();
+        Some(current)
+    }
+}
+
+/// A broken reference between blocks found by [`BlockGraph::validate`]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GraphError {
+    #[error("Block {block} is followed by the missing block {next}")]
+    DanglingNext { block: Id, next: Id },
+
+    #[error("Block {block} has the missing block {parent} as its parent")]
+    DanglingParent { block: Id, parent: Id },
+
+    #[error("Input {input} of block {block} holds the missing block {child}")]
+    DanglingInput { block: Id, input: Name, child: Id },
+
+    #[error("Block {block} should have {expected} as its parent")]
+    WrongParent { block: Id, expected: Id },
+
+    #[error("Block {block} neither is top level nor has a parent")]
+    Orphan { block: Id },
+
+    #[error("Block {block} is its own ancestor")]
+    Cycle { block: Id },
+}
+
+fn input_ids(input: &Input) -> impl Iterator<Item = Id> + '_ {
+    let blocks = match input {
+        Input::Simple(_, block) => [Some(block), None],
+        Input::Obscuring(_, block, shadow) => [Some(block), Some(shadow)],
+    };
+    blocks
+        .into_iter()
+        .flatten()
+        .filter_map(|block| match block {
+            IdOrPrimitiveBlock::Id(id) => Some(*id),
+            IdOrPrimitiveBlock::Primitive(_) => None,
+        })
+}
@@ -1,28 +1,62 @@
 use std::fmt;
 use std::fmt::Write;
 
-use serde::de::Error;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::error::data_error;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Id([char; 20]);
 
+/// Why a string isn't a valid [`Id`]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum IdError {
+    #[error("ID '{0}' doesn't consist of 20 chars")]
+    Length(String),
+}
+
 impl TryFrom<String> for Id {
-    type Error = &'static str;
+    type Error = IdError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         let vec: Vec<char> = value.chars().collect();
-
-        if vec.len() != 20 {
-            return Err("ID must consist of 20 chars");
+        match vec.try_into() {
+            Ok(arr) => Ok(Self(arr)),
+            Err(_) => Err(IdError::Length(value)),
         }
+    }
+}
+
+/// The characters Scratch itself builds its IDs from
+const SOUP: &[u8] =
+    b"!#%()*+,-./:;=?@[]^_`{|}~ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
 
-        let arr: [char; 20] = match vec.try_into() {
-            Ok(arr) => arr,
-            Err(_) => return Err("Error while deserializing ID"),
-        };
+/// A deterministic source of unique IDs
+#[derive(Debug, Default)]
+pub struct IdGenerator {
+    next: u64,
+}
+
+impl IdGenerator {
+    pub fn new() -> IdGenerator {
+        Default::default()
+    }
+
+    pub fn generate(&mut self) -> Id {
+        let mut n = self.next;
+        self.next += 1;
+
+        let mut chars = [SOUP[0] as char; 20];
+        for c in chars.iter_mut().rev() {
+            *c = SOUP[(n % SOUP.len() as u64) as usize] as char;
+            n /= SOUP.len() as u64;
+            if n == 0 {
+                break;
+            }
+        }
 
-        Ok(Self(arr))
+        Id(chars)
     }
 }
 
@@ -42,7 +76,7 @@ impl<'de> Deserialize<'de> for Id {
         D: serde::Deserializer<'de>,
     {
         let s: String = Deserialize::deserialize(deserializer)?;
-        let id = Id::try_from(s).map_err(D::Error::custom)?;
+        let id = Id::try_from(s).map_err(data_error)?;
         Ok(id)
     }
 }
@@ -1,27 +1,76 @@
+pub mod archive;
 pub mod block;
+mod error;
 pub mod extension;
+pub mod graph;
 pub mod id;
 pub mod metadata;
 pub mod monitor;
 pub mod project;
 pub mod string_array;
 pub mod target;
+pub mod validate;
 pub mod value;
 
-pub use id::Id;
+pub use archive::Sb3Archive;
+pub use error::{DataError, Error};
+pub use id::{Id, IdGenerator};
 pub use project::Project;
 pub use value::{Number, Value};
 
-use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use block::PrimitiveVisitor;
+use error::data_error;
 
 pub type Name = String;
 pub type Opcode = String;
 pub type Percentage = u16;
 pub type Angle = u16;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The unknown keys of a JSON object, e.g. ones added by other editors or extensions
+///
+/// Structs flatten them into an `extras` field, so they are re-serialized unchanged.
+/// Where a struct flattens an enum, the extras are part of its variants instead.
+pub type Extras = serde_json::Map<String, serde_json::Value>;
+
+#[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum IdOrPrimitiveBlock {
     Id(Id),
     Primitive(block::PrimitiveBlock),
 }
+
+impl<'de> Deserialize<'de> for IdOrPrimitiveBlock {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct InputVisitor;
+
+        impl<'de> Visitor<'de> for InputVisitor {
+            type Value = IdOrPrimitiveBlock;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a block ID or a primitive block")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<IdOrPrimitiveBlock, E> {
+                Id::try_from(value.to_string())
+                    .map(IdOrPrimitiveBlock::Id)
+                    .map_err(data_error)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<IdOrPrimitiveBlock, A::Error> {
+                PrimitiveVisitor
+                    .visit_seq(seq)
+                    .map(IdOrPrimitiveBlock::Primitive)
+            }
+        }
+
+        deserializer.deserialize_any(InputVisitor)
+    }
+}
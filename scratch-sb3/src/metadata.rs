@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::Extras;
+
 pub const DEFAULT_SEMVER: &str = "3.0.0";
 pub const DEFAULT_VM: &str = "1.5.91";
 pub const DEFAULT_AGENT: &str = "Mozilla/5 (X11; U; Linux x86_64; en-US) Gecko/2010 Firefox/115";
@@ -12,6 +14,9 @@ pub struct Metadata {
     pub vm: String,
     #[serde(default = "default_agent")]
     pub agent: String,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 impl Metadata {
@@ -26,6 +31,7 @@ impl Default for Metadata {
             semver: default_semver(),
             vm: default_vm(),
             agent: default_agent(),
+            extras: Extras::new(),
         }
     }
 }
@@ -77,6 +83,7 @@ pub mod builder {
                 semver: self.semver.unwrap_or(default_semver()),
                 vm: self.vm.unwrap_or(default_vm()),
                 agent: self.agent.unwrap_or(default_agent()),
+                extras: Extras::new(),
             }
         }
     }
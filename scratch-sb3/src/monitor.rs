@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Id, Name, Number, Opcode, Value};
+use crate::{Extras, Id, Name, Number, Opcode, Value};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,6 +22,9 @@ pub struct Monitor {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(flatten)]
     pub slider: Option<Slider>,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
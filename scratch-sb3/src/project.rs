@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+use crate::error::ParseScope;
 use crate::extension::Extension;
 use crate::metadata::Metadata;
 use crate::monitor::Monitor;
 use crate::target::Target;
+use crate::validate::{self, ValidationError};
+use crate::{Error, Extras};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -12,6 +15,9 @@ pub struct Project {
     pub monitors: Vec<Monitor>,
     pub extensions: Vec<Extension>,
     pub meta: Metadata,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 impl Project {
@@ -19,12 +25,18 @@ impl Project {
         builder::ProjectBuilder::new(stage)
     }
 
-    // TODO: error
-    pub fn parse(input: &str) -> Result<Project, String> {
-        match serde_json::from_str(input) {
-            Ok(m) => Ok(m),
-            Err(err) => Err(format!("{}", err)),
-        }
+    pub fn parse(input: &str) -> Result<Project, Error> {
+        let _scope = ParseScope::enter();
+        let mut deserializer = serde_json::Deserializer::from_str(input);
+        let project = serde_path_to_error::deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(project)
+    }
+
+    /// Find inconsistencies Scratch would reject or silently break on, e.g. references to missing
+    /// variables or a wrong number of stages
+    pub fn validate(&self) -> Vec<ValidationError> {
+        validate::project(self)
     }
 
     pub fn to_json(&self) -> String {
@@ -82,6 +94,7 @@ pub mod builder {
                 monitors: self.monitors,
                 extensions: self.extensions,
                 meta: self.meta.unwrap_or_default(),
+                extras: Extras::new(),
             }
         }
     }
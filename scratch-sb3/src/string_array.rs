@@ -1,25 +1,42 @@
 use std::fmt::{Debug, Display};
 
-use serde::de::Error;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use crate::Id;
+use crate::error::data_error;
+use crate::id::{Id, IdError};
+
+/// Why a string isn't a valid [`StringArray`]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum StringArrayError {
+    #[error("string array must start with `[`")]
+    MissingOpenBracket,
+
+    #[error("string array must end with `]`")]
+    MissingCloseBracket,
+
+    #[error("string array elements must be quoted")]
+    UnquotedElement,
+
+    #[error("string array contains an invalid element: {0}")]
+    InvalidElement(#[from] IdError),
+}
 
 pub trait StringArrayElement: Display {
-    fn from_str(s: &str) -> Result<Self, &'static str>
+    fn from_str(s: &str) -> Result<Self, StringArrayError>
     where
         Self: Sized;
 }
 
 impl StringArrayElement for String {
-    fn from_str(s: &str) -> Result<Self, &'static str> {
+    fn from_str(s: &str) -> Result<Self, StringArrayError> {
         Ok(s.to_string())
     }
 }
 
 impl StringArrayElement for Id {
-    fn from_str(s: &str) -> Result<Self, &'static str> {
-        s.to_string().try_into()
+    fn from_str(s: &str) -> Result<Self, StringArrayError> {
+        Ok(Id::try_from(s.to_string())?)
     }
 }
 
@@ -68,11 +85,11 @@ impl<'de, T: StringArrayElement> Deserialize<'de> for StringArray<T> {
         let s: String = Deserialize::deserialize(deserializer)?;
 
         let Some(s) = s.strip_prefix("[") else {
-            return Err(D::Error::custom("Must start with `[`"));
+            return Err(data_error(StringArrayError::MissingOpenBracket));
         };
 
         let Some(s) = s.strip_suffix("]") else {
-            return Err(D::Error::custom("Must end with `]`"));
+            return Err(data_error(StringArrayError::MissingCloseBracket));
         };
 
         let s = deescape(s);
@@ -84,7 +101,7 @@ impl<'de, T: StringArrayElement> Deserialize<'de> for StringArray<T> {
         for (i, mut element) in elements.enumerate() {
             if i == number - 1 {
                 let Some(stripped_element) = element.strip_suffix("\"") else {
-                    return Err(D::Error::custom("Element must end with `\\\"`"));
+                    return Err(data_error(StringArrayError::UnquotedElement));
                 };
                 element = stripped_element;
             }
@@ -92,11 +109,11 @@ impl<'de, T: StringArrayElement> Deserialize<'de> for StringArray<T> {
             if let Some(element) = element.strip_prefix("\"") {
                 let t = match T::from_str(element) {
                     Ok(t) => t,
-                    Err(err) => return Err(D::Error::custom(err)),
+                    Err(err) => return Err(data_error(err)),
                 };
                 stringarray.push(t);
             } else {
-                return Err(D::Error::custom("Element must start with `\\\"`"));
+                return Err(data_error(StringArrayError::UnquotedElement));
             }
         }
 
@@ -1,9 +1,14 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::block::Block;
-use crate::{Angle, Id, Name, Number, Percentage, Value};
+use crate::graph::BlockGraph;
+use crate::{Angle, Extras, Id, Name, Number, Percentage, Value};
+
+pub use builder::{SpriteBuilder, StageBuilder};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -29,6 +34,15 @@ impl Target {
     pub fn stage_builder() -> builder::StageBuilder {
         builder::StageBuilder::default()
     }
+
+    pub fn sprite_builder(name: Name) -> builder::SpriteBuilder {
+        builder::SpriteBuilder::new(name)
+    }
+
+    /// Traverse the blocks as scripts instead of a flat map
+    pub fn graph(&self) -> BlockGraph<'_> {
+        BlockGraph::new(&self.blocks)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,6 +59,9 @@ pub struct StageTarget {
     pub video_state: VideoState,
     pub video_transparency: Percentage,
     pub text_to_speech_language: Option<Language>,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -57,6 +74,9 @@ pub struct SpriteTarget {
     pub direction: Angle,
     pub draggable: bool,
     pub rotation_style: RotationStyle,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -66,6 +86,23 @@ pub enum Variable {
     MaybeCloud(Name, Value, bool),
 }
 
+impl Variable {
+    pub fn new(name: Name, value: Value) -> Variable {
+        Variable::Simple(name, value)
+    }
+
+    /// A cloud variable, its name gets prefixed with [`CLOUD_PREFIX`]
+    pub fn cloud(name: Name, value: Value) -> Variable {
+        Variable::MaybeCloud(format!("{CLOUD_PREFIX}{name}"), value, true)
+    }
+}
+
+/// The prefix Scratch expects in front of the names of cloud variables
+pub const CLOUD_PREFIX: &str = "☁ ";
+
+/// The maximum number of cloud variables in a project
+pub const MAX_CLOUD_VARIABLES: usize = 10;
+
 pub type List = (Name, Vec<Value>);
 pub type Broadcast = Name;
 
@@ -79,6 +116,9 @@ pub struct Comment {
     pub height: Number,
     pub minimized: bool,
     pub text: String,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -107,6 +147,7 @@ impl Asset {
                 bitmap_resolution: None,
                 rotation_center_x: Number::Integer(240),
                 rotation_center_y: Number::Integer(180),
+                extras: Extras::new(),
             }),
         }
     }
@@ -123,6 +164,7 @@ impl Asset {
             asset_type: AssetType::Sound(SoundAsset {
                 rate: 42.0,
                 sample_count: 1,
+                extras: Extras::new(),
             }),
         }
     }
@@ -142,6 +184,9 @@ pub struct CostumeAsset {
     pub bitmap_resolution: Option<Number>,
     pub rotation_center_x: Number,
     pub rotation_center_y: Number,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -149,9 +194,12 @@ pub struct CostumeAsset {
 pub struct SoundAsset {
     pub rate: f32,
     pub sample_count: u32,
+
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum VideoState {
     On,
@@ -159,35 +207,122 @@ pub enum VideoState {
     OnFlipped,
 }
 
-// TODO: incomplete
-#[derive(Debug, Serialize, Deserialize)]
+/// A language of the text to speech extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "&'static str")]
 pub enum Language {
+    Arabic,
+    ChineseMan,
     Danish,
     Dutch,
     English,
     French,
     German,
+    Hindi,
     Icelandic,
     Italian,
     Japanese,
+    Korean,
+    Norwegian,
     Polish,
     PortugueseBr,
     PortugueseEu,
+    Romanian,
     Russian,
     SpanishEu,
     SpanishLat,
-    ChineseMan,
-    Korean,
-    Norwegian,
-    Romanian,
     Swedish,
     Turkish,
     Welsh,
-    Hindi,
-    Arabic,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Language {
+    pub const ALL: [Language; 23] = [
+        Language::Arabic,
+        Language::ChineseMan,
+        Language::Danish,
+        Language::Dutch,
+        Language::English,
+        Language::French,
+        Language::German,
+        Language::Hindi,
+        Language::Icelandic,
+        Language::Italian,
+        Language::Japanese,
+        Language::Korean,
+        Language::Norwegian,
+        Language::Polish,
+        Language::PortugueseBr,
+        Language::PortugueseEu,
+        Language::Romanian,
+        Language::Russian,
+        Language::SpanishEu,
+        Language::SpanishLat,
+        Language::Swedish,
+        Language::Turkish,
+        Language::Welsh,
+    ];
+
+    /// The code Scratch stores the language as
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Arabic => "ar",
+            Language::ChineseMan => "zh-cn",
+            Language::Danish => "da",
+            Language::Dutch => "nl",
+            Language::English => "en",
+            Language::French => "fr",
+            Language::German => "de",
+            Language::Hindi => "hi",
+            Language::Icelandic => "is",
+            Language::Italian => "it",
+            Language::Japanese => "ja",
+            Language::Korean => "ko",
+            Language::Norwegian => "nb",
+            Language::Polish => "pl",
+            Language::PortugueseBr => "pt-br",
+            Language::PortugueseEu => "pt",
+            Language::Romanian => "ro",
+            Language::Russian => "ru",
+            Language::SpanishEu => "es",
+            Language::SpanishLat => "es-419",
+            Language::Swedish => "sv",
+            Language::Turkish => "tr",
+            Language::Welsh => "cy",
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = UnknownLanguage;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Language::ALL
+            .into_iter()
+            .find(|language| language.code() == code)
+            .ok_or_else(|| UnknownLanguage(code.to_string()))
+    }
+}
+
+impl TryFrom<String> for Language {
+    type Error = UnknownLanguage;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        code.parse()
+    }
+}
+
+impl From<Language> for &'static str {
+    fn from(language: Language) -> Self {
+        language.code()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Unknown text to speech language '{0}'")]
+pub struct UnknownLanguage(pub String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RotationStyle {
     #[serde(rename = "all around")]
     AllAround,
@@ -240,11 +375,52 @@ mod builder {
     }
 
     impl StageBuilder {
+        pub fn add_variable(mut self, id: Id, variable: Variable) -> StageBuilder {
+            self.variables.insert(id, variable);
+            self
+        }
+
+        pub fn add_list(mut self, id: Id, list: List) -> StageBuilder {
+            self.lists.insert(id, list);
+            self
+        }
+
+        pub fn add_broadcast(mut self, id: Id, broadcast: Broadcast) -> StageBuilder {
+            self.broadcasts.insert(id, broadcast);
+            self
+        }
+
+        pub fn add_block(mut self, id: Id, block: Block) -> StageBuilder {
+            self.blocks.insert(id, block);
+            self
+        }
+
         pub fn volume(mut self, volume: Percentage) -> StageBuilder {
             self.volume = volume;
             self
         }
 
+        /// Beats per minute of the music extension
+        pub fn tempo(mut self, tempo: u32) -> StageBuilder {
+            self.tempo = tempo;
+            self
+        }
+
+        pub fn video_state(mut self, video_state: VideoState) -> StageBuilder {
+            self.video_state = video_state;
+            self
+        }
+
+        pub fn video_transparency(mut self, video_transparency: Percentage) -> StageBuilder {
+            self.video_transparency = video_transparency;
+            self
+        }
+
+        pub fn text_to_speech_language(mut self, language: Language) -> StageBuilder {
+            self.text_to_speech_language = Some(language);
+            self
+        }
+
         pub fn add_costume(mut self, costume: Asset) -> StageBuilder {
             self.costumes.push(costume);
             self
@@ -268,6 +444,7 @@ mod builder {
                 video_state: self.video_state,
                 video_transparency: self.video_transparency,
                 text_to_speech_language: self.text_to_speech_language,
+                extras: Extras::new(),
             };
 
             Target {
@@ -287,4 +464,158 @@ mod builder {
             }
         }
     }
+
+    #[derive(Debug)]
+    pub struct SpriteBuilder {
+        name: Name,
+        variables: HashMap<Id, Variable>,
+        lists: HashMap<Id, List>,
+        blocks: HashMap<Id, Block>,
+        comments: HashMap<Id, Comment>,
+        current_costume: Option<u32>,
+        costumes: Vec<Asset>,
+        sounds: Vec<Asset>,
+        layer_order: u32,
+        volume: Percentage,
+        visible: bool,
+        x: Number,
+        y: Number,
+        size: Percentage,
+        direction: Angle,
+        draggable: bool,
+        rotation_style: RotationStyle,
+    }
+
+    impl SpriteBuilder {
+        pub fn new(name: Name) -> SpriteBuilder {
+            SpriteBuilder {
+                name,
+                variables: Default::default(),
+                lists: Default::default(),
+                blocks: Default::default(),
+                comments: Default::default(),
+                current_costume: None,
+                costumes: Default::default(),
+                sounds: Default::default(),
+                layer_order: 1,
+                volume: 100,
+                visible: true,
+                x: Number::Integer(0),
+                y: Number::Integer(0),
+                size: 100,
+                direction: 90,
+                draggable: false,
+                rotation_style: RotationStyle::AllAround,
+            }
+        }
+
+        pub fn add_variable(mut self, id: Id, variable: Variable) -> SpriteBuilder {
+            self.variables.insert(id, variable);
+            self
+        }
+
+        pub fn add_list(mut self, id: Id, list: List) -> SpriteBuilder {
+            self.lists.insert(id, list);
+            self
+        }
+
+        pub fn add_block(mut self, id: Id, block: Block) -> SpriteBuilder {
+            self.blocks.insert(id, block);
+            self
+        }
+
+        pub fn add_costume(mut self, costume: Asset) -> SpriteBuilder {
+            self.costumes.push(costume);
+            self
+        }
+
+        pub fn current_costume(mut self, index: Option<u32>) -> SpriteBuilder {
+            self.current_costume = index;
+            self
+        }
+
+        pub fn add_sound(mut self, sound: Asset) -> SpriteBuilder {
+            self.sounds.push(sound);
+            self
+        }
+
+        /// Position among the sprites, `1` is the back-most layer right in front of the stage
+        pub fn layer_order(mut self, layer_order: u32) -> SpriteBuilder {
+            self.layer_order = layer_order;
+            self
+        }
+
+        pub fn volume(mut self, volume: Percentage) -> SpriteBuilder {
+            self.volume = volume;
+            self
+        }
+
+        pub fn visible(mut self, visible: bool) -> SpriteBuilder {
+            self.visible = visible;
+            self
+        }
+
+        pub fn x(mut self, x: Number) -> SpriteBuilder {
+            self.x = x;
+            self
+        }
+
+        pub fn y(mut self, y: Number) -> SpriteBuilder {
+            self.y = y;
+            self
+        }
+
+        pub fn size(mut self, size: Percentage) -> SpriteBuilder {
+            self.size = size;
+            self
+        }
+
+        /// Direction in degrees, `0` is up and `90` (the default) is right
+        pub fn direction(mut self, direction: Angle) -> SpriteBuilder {
+            self.direction = direction;
+            self
+        }
+
+        pub fn draggable(mut self, draggable: bool) -> SpriteBuilder {
+            self.draggable = draggable;
+            self
+        }
+
+        pub fn rotation_style(mut self, rotation_style: RotationStyle) -> SpriteBuilder {
+            self.rotation_style = rotation_style;
+            self
+        }
+
+        pub fn build(self) -> Target {
+            debug_assert!(!self.costumes.is_empty(), "Target without costume");
+
+            let sprite = SpriteTarget {
+                visible: self.visible,
+                x: self.x,
+                y: self.y,
+                size: self.size,
+                direction: self.direction,
+                draggable: self.draggable,
+                rotation_style: self.rotation_style,
+                extras: Extras::new(),
+            };
+
+            Target {
+                is_stage: false,
+                name: self.name,
+                variables: self.variables,
+                lists: self.lists,
+                // broadcasts always live on the stage
+                broadcasts: HashMap::new(),
+                blocks: self.blocks,
+                comments: self.comments,
+                current_costume: self.current_costume.unwrap_or(0),
+                costumes: self.costumes,
+                sounds: self.sounds,
+                layer_order: self.layer_order,
+                volume: self.volume,
+                target_type: TargetType::Sprite(sprite),
+            }
+        }
+    }
 }
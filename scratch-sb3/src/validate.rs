@@ -0,0 +1,220 @@
+//! Checks for inconsistencies Scratch doesn't report, but refuses to load or silently corrupts
+
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use crate::block::{Block, Input, PrimitiveBlock, ReferenceType};
+use crate::graph::GraphError;
+use crate::target::Target;
+use crate::{Id, IdOrPrimitiveBlock, Name, Project, Value};
+
+/// An inconsistency found by [`Project::validate`]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationError {
+    #[error("Project has {0} stage targets instead of exactly one")]
+    StageCount(usize),
+
+    #[error("There are multiple sprites named '{0}'")]
+    DuplicateSprite(Name),
+
+    #[error("Current costume {index} of '{target}' is out of its {count} costumes")]
+    CurrentCostume {
+        target: Name,
+        index: u32,
+        count: usize,
+    },
+
+    #[error("Asset '{asset}' of '{target}' is stored as '{md5ext}' instead of '{expected}'")]
+    AssetFilename {
+        target: Name,
+        asset: Name,
+        md5ext: String,
+        expected: String,
+    },
+
+    #[error("Block {block} of '{target}' refers to the missing {kind} '{name}' ({id})")]
+    MissingSymbol {
+        target: Name,
+        block: Id,
+        kind: &'static str,
+        name: Name,
+        id: Id,
+    },
+
+    #[error("Monitor {id} shows the missing {kind} '{name}'")]
+    MonitorTarget {
+        id: Id,
+        kind: &'static str,
+        name: String,
+    },
+
+    #[error("Invalid blocks in '{target}': {error}")]
+    Blocks {
+        target: Name,
+        #[source]
+        error: GraphError,
+    },
+}
+
+pub(crate) fn project(project: &Project) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    let stages: Vec<_> = project.targets.iter().filter(|t| t.is_stage).collect();
+    if stages.len() != 1 {
+        errors.push(ValidationError::StageCount(stages.len()));
+    }
+    let stage = stages.first().copied();
+
+    let mut sprite_names = HashSet::new();
+    for target in project.targets.iter().filter(|t| !t.is_stage) {
+        if !sprite_names.insert(&target.name) {
+            errors.push(ValidationError::DuplicateSprite(target.name.clone()));
+        }
+    }
+
+    for target in &project.targets {
+        assets(target, &mut errors);
+        symbols(target, stage, &mut errors);
+
+        errors.extend(
+            target
+                .graph()
+                .validate()
+                .into_iter()
+                .map(|error| ValidationError::Blocks {
+                    target: target.name.clone(),
+                    error,
+                }),
+        );
+    }
+
+    for monitor in &project.monitors {
+        let kind = match monitor.opcode.as_str() {
+            "data_variable" => ReferenceType::Variable,
+            "data_listcontents" => ReferenceType::List,
+            _ => continue,
+        };
+        let owner = match &monitor.sprite_name {
+            Some(name) => project
+                .targets
+                .iter()
+                .find(|t| !t.is_stage && &t.name == name),
+            None => stage,
+        };
+
+        if !owner.is_some_and(|owner| declares(owner, kind, monitor.id)) {
+            errors.push(ValidationError::MonitorTarget {
+                id: monitor.id,
+                kind: kind_name(kind),
+                name: monitor.params.values().next().cloned().unwrap_or_default(),
+            });
+        }
+    }
+
+    errors
+}
+
+fn assets(target: &Target, errors: &mut Vec<ValidationError>) {
+    if target.current_costume as usize >= target.costumes.len() {
+        errors.push(ValidationError::CurrentCostume {
+            target: target.name.clone(),
+            index: target.current_costume,
+            count: target.costumes.len(),
+        });
+    }
+
+    for asset in target.costumes.iter().chain(&target.sounds) {
+        let expected = format!("{}.{}", asset.asset_id, asset.data_format);
+        if asset.md5ext != expected {
+            errors.push(ValidationError::AssetFilename {
+                target: target.name.clone(),
+                asset: asset.name.clone(),
+                md5ext: asset.md5ext.clone(),
+                expected,
+            });
+        }
+    }
+}
+
+/// Check that the variables, lists and broadcasts used by the blocks exist in the target or the stage
+fn symbols(target: &Target, stage: Option<&Target>, errors: &mut Vec<ValidationError>) {
+    let mut check = |block: Id, kind: ReferenceType, name: &Name, id: Id| {
+        let found = declares(target, kind, id) || stage.is_some_and(|s| declares(s, kind, id));
+        if !found {
+            errors.push(ValidationError::MissingSymbol {
+                target: target.name.clone(),
+                block,
+                kind: kind_name(kind),
+                name: name.clone(),
+                id,
+            });
+        }
+    };
+
+    for (&id, block) in &target.blocks {
+        let block = match block {
+            Block::Full(block) => block,
+            Block::Primitive(primitive) => {
+                if let Some((kind, name, symbol)) = reference(primitive) {
+                    check(id, kind, name, symbol);
+                }
+                continue;
+            }
+        };
+
+        for (field, (value, symbol)) in &block.fields {
+            let kind = match field.as_str() {
+                "VARIABLE" => ReferenceType::Variable,
+                "LIST" => ReferenceType::List,
+                "BROADCAST_OPTION" => ReferenceType::Broadcast,
+                _ => continue,
+            };
+            if let Some(symbol) = symbol {
+                let name = match value {
+                    Value::String(name) => name.clone(),
+                    Value::Number(number) => number.to_string(),
+                };
+                check(id, kind, &name, *symbol);
+            }
+        }
+
+        for input in block.inputs.values() {
+            let primitives = match input {
+                Input::Simple(_, input) => [Some(input), None],
+                Input::Obscuring(_, input, shadow) => [Some(input), Some(shadow)],
+            };
+            for primitive in primitives.into_iter().flatten() {
+                if let IdOrPrimitiveBlock::Primitive(primitive) = primitive
+                    && let Some((kind, name, symbol)) = reference(primitive)
+                {
+                    check(id, kind, name, symbol);
+                }
+            }
+        }
+    }
+}
+
+fn reference(primitive: &PrimitiveBlock) -> Option<(ReferenceType, &Name, Id)> {
+    match primitive {
+        PrimitiveBlock::Simple(..) => None,
+        PrimitiveBlock::Advanced(kind, name, id)
+        | PrimitiveBlock::AdvancedWithPos(kind, name, id, ..) => Some((*kind, name, *id)),
+    }
+}
+
+fn declares(target: &Target, kind: ReferenceType, id: Id) -> bool {
+    match kind {
+        ReferenceType::Variable => target.variables.contains_key(&id),
+        ReferenceType::List => target.lists.contains_key(&id),
+        ReferenceType::Broadcast => target.broadcasts.contains_key(&id),
+    }
+}
+
+fn kind_name(kind: ReferenceType) -> &'static str {
+    match kind {
+        ReferenceType::Variable => "variable",
+        ReferenceType::List => "list",
+        ReferenceType::Broadcast => "broadcast",
+    }
+}
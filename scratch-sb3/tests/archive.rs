@@ -0,0 +1,40 @@
+use std::io::Cursor;
+
+use scratch_sb3::archive::ArchiveError;
+use scratch_sb3::{Project, Sb3Archive};
+
+#[test]
+fn archive_round_trip() {
+    let project = Project::parse(include_str!("./project.json")).unwrap();
+    let mut archive = Sb3Archive::new(project);
+    archive.add_asset(
+        "cd21514d0531fdffb22204e0ec5ed84a.svg".to_string(),
+        b"<svg></svg>".to_vec(),
+    );
+
+    let mut buffer = Cursor::new(Vec::new());
+    archive.write(&mut buffer).unwrap();
+    buffer.set_position(0);
+
+    let read = Sb3Archive::read(buffer).unwrap();
+    assert_eq!(read.assets, archive.assets);
+    let json = |project: &Project| -> serde_json::Value {
+        serde_json::from_str(&project.to_json()).unwrap()
+    };
+    assert_eq!(json(&read.project), json(&archive.project));
+}
+
+#[test]
+fn archive_without_project() {
+    let mut buffer = Cursor::new(Vec::new());
+    let mut zip = zip::ZipWriter::new(&mut buffer);
+    zip.start_file("asset.svg", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.finish().unwrap();
+    buffer.set_position(0);
+
+    assert!(matches!(
+        Sb3Archive::read(buffer),
+        Err(ArchiveError::MissingProject)
+    ));
+}
@@ -0,0 +1,90 @@
+use scratch_sb3::Project;
+use scratch_sb3::graph::{GraphError, InputRef};
+
+fn fixture() -> serde_json::Value {
+    serde_json::from_str(include_str!("./project.json")).unwrap()
+}
+
+fn opcodes<'a>(blocks: impl IntoIterator<Item = scratch_sb3::graph::BlockRef<'a>>) -> Vec<&'a str> {
+    blocks
+        .into_iter()
+        .map(|block| block.block().opcode.as_str())
+        .collect()
+}
+
+#[test]
+fn traverse() {
+    let project = Project::parse(&fixture().to_string()).unwrap();
+
+    let stage = project.targets[0].graph();
+    assert_eq!(
+        opcodes(stage.scripts()),
+        ["procedures_definition", "operator_equals"]
+    );
+    let definition = stage.scripts()[0];
+    let Some(InputRef::Block(prototype)) = definition.input("custom_block") else {
+        panic!("expected the prototype");
+    };
+    assert_eq!(prototype.block().opcode, "procedures_prototype");
+    assert_eq!(prototype.parent().unwrap().id(), definition.id());
+    assert!(matches!(
+        definition.shadow("custom_block"),
+        Some(InputRef::Block(_))
+    ));
+
+    let sprite = project.targets[1].graph();
+    let [script] = sprite.scripts()[..] else {
+        panic!("expected a single script");
+    };
+    assert_eq!(
+        opcodes(script.stack()),
+        ["event_whenflagclicked", "motion_movesteps"]
+    );
+    let steps = script.next().unwrap();
+    assert!(matches!(steps.input("STEPS"), Some(InputRef::Primitive(_))));
+    assert_eq!(steps.substack("SUBSTACK").count(), 0);
+
+    for target in &project.targets {
+        assert_eq!(target.graph().validate(), []);
+    }
+}
+
+#[test]
+fn referential_integrity() {
+    let validate = |edit: fn(&mut serde_json::Value)| {
+        let mut json = fixture();
+        edit(&mut json["targets"][1]["blocks"]);
+        let project = Project::parse(&json.to_string()).unwrap();
+        project.targets[1].graph().validate()
+    };
+    let hat = "ks3N!^X4F#xHUnXFF,t5".to_string().try_into().unwrap();
+    let steps = "Y{,v8.r@U6v[3k$8|pN@".to_string().try_into().unwrap();
+    let missing = "aaaaaaaaaaaaaaaaaaaa".to_string().try_into().unwrap();
+
+    let errors =
+        validate(|blocks| blocks["ks3N!^X4F#xHUnXFF,t5"]["next"] = "aaaaaaaaaaaaaaaaaaaa".into());
+    assert_eq!(
+        errors,
+        [GraphError::DanglingNext {
+            block: hat,
+            next: missing
+        }]
+    );
+
+    let errors =
+        validate(|blocks| blocks["Y{,v8.r@U6v[3k$8|pN@"]["parent"] = serde_json::Value::Null);
+    assert!(errors.contains(&GraphError::Orphan { block: steps }));
+    assert!(errors.contains(&GraphError::WrongParent {
+        block: steps,
+        expected: hat
+    }));
+
+    let errors = validate(|blocks| {
+        blocks["ks3N!^X4F#xHUnXFF,t5"]["topLevel"] = false.into();
+        blocks["ks3N!^X4F#xHUnXFF,t5"]["parent"] = "Y{,v8.r@U6v[3k$8|pN@".into();
+    });
+    assert!(
+        errors.contains(&GraphError::Cycle { block: hat })
+            || errors.contains(&GraphError::Cycle { block: steps })
+    );
+}
@@ -1,7 +1,248 @@
-use scratch_sb3::Project;
+use scratch_sb3::block::{
+    Input, Mutation, MutationType, PrimitiveBlock, ReferenceType, ShadowType, ValueType,
+};
+use scratch_sb3::extension::Extension;
+use scratch_sb3::id::IdError;
+use scratch_sb3::string_array::{StringArray, StringArrayError};
+use scratch_sb3::target::Language;
+use scratch_sb3::{DataError, Error, Id, Number, Project};
 
 #[test]
 fn parse_scratch_manifest() {
     let input = include_str!("./project.json");
     Project::parse(input).expect("Error while parsing Scratch manifest");
 }
+
+#[test]
+fn extension_ids() {
+    let extensions: Vec<Extension> =
+        serde_json::from_str(r#"["pen", "videoSensing", "text2speech"]"#).unwrap();
+    assert_eq!(
+        extensions,
+        [
+            Extension::Pen,
+            Extension::VideoSensing,
+            Extension::Text2speech
+        ]
+    );
+
+    assert_eq!(
+        Extension::of_opcode("makeymakey_whenMakeyKeyPressed"),
+        Some(Extension::Makeymakey)
+    );
+    assert_eq!(Extension::of_opcode("event_whenflagclicked"), None);
+}
+
+#[test]
+fn parse_errors() {
+    let fixture: serde_json::Value = serde_json::from_str(include_str!("./project.json")).unwrap();
+    let parse = |edit: fn(&mut serde_json::Value)| {
+        let mut json = fixture.clone();
+        edit(&mut json);
+        Project::parse(&json.to_string()).unwrap_err()
+    };
+
+    let err = Project::parse(r#"{"targets": ["#).unwrap_err();
+    assert!(matches!(err, Error::Syntax { line: 1, .. }), "{err}");
+
+    let err = parse(|json| json["targets"][1]["layerOrder"] = "top".into());
+    assert!(
+        matches!(&err, Error::Schema { path, .. } if path == "targets[1].layerOrder"),
+        "{err}"
+    );
+
+    let err =
+        parse(|json| json["targets"][1]["blocks"]["Y{,v8.r@U6v[3k$8|pN@"]["next"] = "short".into());
+    assert!(
+        matches!(
+            &err,
+            Error::Data { path, source: DataError::Id(IdError::Length(id)), .. }
+                if path == "targets[1].blocks.Y{,v8.r@U6v[3k$8|pN@.next" && id == "short"
+        ),
+        "{err}"
+    );
+
+    let err = parse(|json| {
+        json["targets"][1]["blocks"]["Y{,v8.r@U6v[3k$8|pN@"]["inputs"]["STEPS"] =
+            serde_json::json!([1, [14, "10"]])
+    });
+    assert!(
+        matches!(&err, Error::Schema { path, .. } if path == "targets[1].blocks.Y{,v8.r@U6v[3k$8|pN@.inputs.STEPS[1]"),
+        "{err}"
+    );
+
+    let err = parse(|json| {
+        json["targets"][0]["blocks"]["%u?Zm$1$EKgO|f)kWOa_"]["mutation"]["argumentids"] =
+            "[\"short\"]".into()
+    });
+    assert!(
+        matches!(
+            &err,
+            Error::Data { path, source: DataError::StringArray(StringArrayError::InvalidElement(_)), .. }
+                if path == "targets[0].blocks.%u?Zm$1$EKgO|f)kWOa_.mutation.argumentids"
+        ),
+        "{err}"
+    );
+
+    let err = parse(|json| {
+        json["targets"][0]["blocks"]["%u?Zm$1$EKgO|f)kWOa_"]["mutation"]["argumentids"] =
+            "[\"short\"".into()
+    });
+    assert!(
+        matches!(
+            &err,
+            Error::Data {
+                source: DataError::StringArray(StringArrayError::MissingCloseBracket),
+                ..
+            }
+        ),
+        "{err}"
+    );
+
+    // the cause isn't left over for unrelated errors with the same message
+    assert!(serde_json::from_str::<StringArray<Id>>(r#""[\"short\"""#).is_err());
+    let err = <serde_json::Error as serde::de::Error>::custom(
+        StringArrayError::MissingCloseBracket.to_string(),
+    );
+    assert!(matches!(Error::from(err), Error::Schema { .. }));
+}
+
+#[test]
+fn round_trip() {
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(include_str!("./project.json")).unwrap();
+    fixture["extensionURLs"] = serde_json::json!({ "custom": "https://example.com/ext.js" });
+    fixture["targets"][0]["targetPaneOrder"] = 0.into();
+    fixture["targets"][1]["blocks"]["Y{,v8.r@U6v[3k$8|pN@"]["unknown"] = true.into();
+    fixture["targets"][1]["costumes"][0]["unknown"] = "costume".into();
+    fixture["monitors"][0]["unknown"] = serde_json::json!([1, 2]);
+    fixture["targets"][0]["blocks"]["%u?Zm$1$EKgO|f)kWOa_"]["mutation"]["unknown"] = 1.into();
+    fixture["targets"][1]["comments"]["Ti1l91?Y~#iFZ.osYz,:"]["unknown"] = 2.into();
+
+    fixture["meta"]["platform"] =
+        serde_json::json!({ "name": "TurboWarp", "url": "https://turbowarp.org/" });
+
+    let project = Project::parse(&fixture.to_string()).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&project.to_json()).unwrap();
+    assert_eq!(normalize_numbers(json.clone()), normalize_numbers(fixture));
+
+    let reparsed = Project::parse(&project.to_json()).unwrap();
+    let json_again: serde_json::Value = serde_json::from_str(&reparsed.to_json()).unwrap();
+    assert_eq!(json_again, json);
+}
+
+/// Numbers are stored as `i32` or `f32`, so integers may come back as floats like `44100.0`
+/// and floats lose some precision
+fn normalize_numbers(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Number(num) => {
+            let num = num.as_f64().unwrap() as f32;
+            serde_json::Number::from_f64(num as f64).unwrap().into()
+        }
+        serde_json::Value::Array(values) => values.into_iter().map(normalize_numbers).collect(),
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| (key, normalize_numbers(value)))
+            .collect(),
+        value => value,
+    }
+}
+
+#[test]
+fn mutations() {
+    let parse = |json: &str| {
+        serde_json::from_str::<Mutation>(json)
+            .unwrap()
+            .mutation_type
+    };
+
+    let MutationType::ControlStop(stop) =
+        parse(r#"{"tagName": "mutation", "children": [], "hasnext": "true"}"#)
+    else {
+        panic!("expected a control_stop mutation");
+    };
+    assert!(stop.hasnext);
+
+    let prototype = r#"{"tagName": "mutation", "children": [], "proccode": "jump %s",
+        "argumentids": "[\"n9I:#zUc3cP!bB{?J-e4\"]", "argumentnames": "[\"height\"]", "warp": true}"#;
+    let MutationType::Procedure(procedure) = parse(prototype) else {
+        panic!("expected a procedure mutation");
+    };
+    assert!(procedure.warp);
+    assert_eq!(
+        procedure.prototype.unwrap().argumentdefaults,
+        StringArray::new()
+    );
+
+    let extension =
+        r#"{"tagName": "mutation", "children": [{"tagName": "arg"}], "blockInfo": "{}"}"#;
+    let MutationType::Other(extras) = parse(extension) else {
+        panic!("expected an unknown mutation");
+    };
+    assert_eq!(extras["blockInfo"], "{}");
+
+    let invalid = r#"{"tagName": "mutation", "children": [], "proccode": "jump", "argumentids": "[", "warp": "false"}"#;
+    assert!(serde_json::from_str::<Mutation>(invalid).is_err());
+}
+
+#[test]
+fn primitive_codes() {
+    let primitive = |json: &str| serde_json::from_str::<PrimitiveBlock>(json);
+    let input = |json: &str| serde_json::from_str::<Input>(json);
+
+    assert!(matches!(
+        primitive(r#"[5, "1.5"]"#).unwrap(),
+        PrimitiveBlock::Simple(ValueType::PositiveNumber, _)
+    ));
+    assert!(matches!(
+        primitive(r#"[13, "items", "n9I:#zUc3cP!bB{?J-e4", 10, 20]"#).unwrap(),
+        PrimitiveBlock::AdvancedWithPos(ReferenceType::List, ..)
+    ));
+
+    let err = primitive(r#"[14, "1"]"#).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("invalid value: integer `14`, expected a primitive type from 4 to 13"),
+        "{err}"
+    );
+    assert!(primitive(r#"[11, "message"]"#).is_err());
+    assert!(primitive(r#"[4, "message", "n9I:#zUc3cP!bB{?J-e4"]"#).is_err());
+
+    assert!(matches!(
+        input(r#"[3, "n9I:#zUc3cP!bB{?J-e4", [10, ""]]"#).unwrap(),
+        Input::Obscuring(ShadowType::ObscuredShadow, ..)
+    ));
+    assert!(input(r#"[3, [10, ""]]"#).is_err());
+    assert!(input(r#"[1, "n9I:#zUc3cP!bB{?J-e4", [10, ""]]"#).is_err());
+    assert!(input(r#"[0, [10, ""]]"#).is_err());
+
+    let block = PrimitiveBlock::builder().positive_number(Number::Float(1.5));
+    assert_eq!(serde_json::to_string(&block.unwrap()).unwrap(), "[5,1.5]");
+    assert!(
+        PrimitiveBlock::builder()
+            .positive_number(Number::Integer(-5))
+            .is_none()
+    );
+    assert!(
+        PrimitiveBlock::builder()
+            .positive_number(Number::Float(f32::NAN))
+            .is_none()
+    );
+}
+
+#[test]
+fn languages() {
+    assert_eq!(
+        serde_json::from_str::<Language>(r#""es-419""#).unwrap(),
+        Language::SpanishLat
+    );
+    assert_eq!(
+        serde_json::to_string(&Language::ChineseMan).unwrap(),
+        r#""zh-cn""#
+    );
+    assert!(serde_json::from_str::<Language>(r#""English""#).is_err());
+
+    for language in Language::ALL {
+        assert_eq!(language.code().parse(), Ok(language));
+    }
+}
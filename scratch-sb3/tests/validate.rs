@@ -0,0 +1,64 @@
+use scratch_sb3::Project;
+use scratch_sb3::validate::ValidationError;
+
+fn validate(edit: fn(&mut serde_json::Value)) -> Vec<ValidationError> {
+    let mut json: serde_json::Value = serde_json::from_str(include_str!("./project.json")).unwrap();
+    edit(&mut json);
+    Project::parse(&json.to_string()).unwrap().validate()
+}
+
+#[test]
+fn valid_fixture() {
+    assert_eq!(validate(|_| {}), []);
+}
+
+#[test]
+fn targets() {
+    let errors = validate(|json| json["targets"][0]["isStage"] = false.into());
+    assert!(
+        errors.contains(&ValidationError::StageCount(0)),
+        "{errors:?}"
+    );
+
+    let errors = validate(|json| json["targets"][2]["name"] = "Sprite1".into());
+    assert_eq!(errors, [ValidationError::DuplicateSprite("Sprite1".into())]);
+
+    let errors = validate(|json| json["targets"][1]["currentCostume"] = 7.into());
+    assert!(matches!(
+        &errors[..],
+        [ValidationError::CurrentCostume { index: 7, .. }]
+    ));
+
+    let errors = validate(|json| json["targets"][1]["sounds"][0]["md5ext"] = "meow.wav".into());
+    assert!(matches!(
+        &errors[..],
+        [ValidationError::AssetFilename { md5ext, .. }] if md5ext == "meow.wav"
+    ));
+}
+
+#[test]
+fn references() {
+    let errors = validate(|json| {
+        json["targets"][1]["blocks"]["Y{,v8.r@U6v[3k$8|pN@"]["fields"]["VARIABLE"] =
+            serde_json::json!(["score", "aaaaaaaaaaaaaaaaaaaa"]);
+    });
+    assert!(matches!(
+        &errors[..],
+        [ValidationError::MissingSymbol { kind: "variable", name, .. }] if name == "score"
+    ));
+
+    let errors = validate(|json| {
+        json["targets"][1]["blocks"]["Y{,v8.r@U6v[3k$8|pN@"]["inputs"]["STEPS"] =
+            serde_json::json!([3, [13, "items", "aaaaaaaaaaaaaaaaaaaa"], [4, "10"]]);
+    });
+    assert!(matches!(
+        &errors[..],
+        [ValidationError::MissingSymbol { kind: "list", .. }]
+    ));
+
+    let errors = validate(|json| json["monitors"][0]["id"] = "aaaaaaaaaaaaaaaaaaaa".into());
+    assert!(matches!(
+        &errors[..],
+        [ValidationError::MonitorTarget { .. }]
+    ));
+}
@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+
+use super::{AnalysisError, AnalysisWarning, Diagnostics, SymbolKind};
+use crate::catalog::{self, ArgSpec, Shape};
+use crate::parsing::{Arg, BlockCall, Expr, ExprKind, FieldKind, Ident, Script, ScriptKind, Span};
+use crate::parsing::{Stmt, StmtKind};
+
+/// Where the scripts of a target send and receive broadcasts
+#[derive(Debug, Default)]
+pub struct BroadcastUsage {
+    pub sent: Vec<(Ident, Span)>,
+    pub received: Vec<(Ident, Span)>,
+}
+
+impl BroadcastUsage {
+    pub fn new<'a>(scripts: impl IntoIterator<Item = &'a Script>) -> BroadcastUsage {
+        let mut usage = BroadcastUsage::default();
+        for script in scripts {
+            if let ScriptKind::When(hat) = &script.kind {
+                usage.call(hat);
+            }
+            usage.body(&script.body);
+        }
+        usage
+    }
+
+    /// All broadcasts that are sent or received
+    pub fn names(&self) -> impl Iterator<Item = &Ident> {
+        self.sent.iter().chain(&self.received).map(|(name, _)| name)
+    }
+
+    fn body(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::Call(call) => self.call(call),
+                StmtKind::If {
+                    cond,
+                    then,
+                    otherwise,
+                } => {
+                    self.expr(cond);
+                    self.body(then);
+                    if let Some(otherwise) = otherwise {
+                        self.body(otherwise);
+                    }
+                }
+                StmtKind::Repeat { times: expr, body }
+                | StmtKind::RepeatUntil { cond: expr, body }
+                | StmtKind::While { cond: expr, body } => {
+                    self.expr(expr);
+                    self.body(body);
+                }
+                StmtKind::Forever(body) => self.body(body),
+                StmtKind::Match {
+                    value,
+                    arms,
+                    default,
+                } => {
+                    self.expr(value);
+                    for arm in arms {
+                        arm.patterns.iter().for_each(|pattern| self.expr(pattern));
+                        self.body(&arm.body);
+                    }
+                    if let Some(default) = default {
+                        self.body(default);
+                    }
+                }
+                StmtKind::For { from, to, body, .. } => {
+                    self.expr(from);
+                    self.expr(to);
+                    self.body(body);
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, call: &BlockCall) {
+        let spec = call
+            .namespace
+            .as_ref()
+            .and_then(|namespace| catalog::lookup(namespace.as_str(), &call.pattern()));
+
+        for (i, arg) in call.args().enumerate() {
+            match arg {
+                Arg::Reporter(expr) | Arg::Boolean(expr) => self.expr(expr),
+                Arg::Field(field) => {
+                    let Some(spec) = spec else {
+                        continue;
+                    };
+                    let (ArgSpec::Field(field_spec) | ArgSpec::Primitive(field_spec)) =
+                        &spec.args[i]
+                    else {
+                        continue;
+                    };
+
+                    if field_spec.refers != Some(SymbolKind::Broadcast) {
+                        continue;
+                    }
+
+                    let name = match &field.kind {
+                        FieldKind::Ident(name) => name.clone(),
+                        FieldKind::String(string) => Ident::new(string.clone()),
+                    };
+                    match spec.shape {
+                        Shape::Hat => self.received.push((name, field.span)),
+                        _ => self.sent.push((name, field.span)),
+                    }
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(_) | ExprKind::Ident(_) => {}
+            ExprKind::Call(call) => self.call(call),
+            ExprKind::Binary(_, lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+        }
+    }
+}
+
+/// Warn about broadcasts that are sent but never received by any target, or the other way around
+///
+/// The warnings of each target end up at the same index as its usage.
+pub fn check_broadcasts(targets: &[&BroadcastUsage]) -> Vec<Diagnostics> {
+    let sent: HashSet<&Ident> = targets
+        .iter()
+        .flat_map(|usage| &usage.sent)
+        .map(|(name, _)| name)
+        .collect();
+    let received: HashSet<&Ident> = targets
+        .iter()
+        .flat_map(|usage| &usage.received)
+        .map(|(name, _)| name)
+        .collect();
+
+    targets
+        .iter()
+        .map(|usage| {
+            let mut diagnostics = Diagnostics::default();
+            for (name, span) in usage.sent.iter().filter(|(n, _)| !received.contains(n)) {
+                diagnostics.warnings.push(AnalysisWarning::NeverReceived {
+                    name: name.to_string(),
+                    span: *span,
+                });
+            }
+            for (name, span) in usage.received.iter().filter(|(n, _)| !sent.contains(n)) {
+                diagnostics.warnings.push(AnalysisWarning::NeverSent {
+                    name: name.to_string(),
+                    span: *span,
+                });
+            }
+            diagnostics
+        })
+        .collect()
+}
+
+/// Report the broadcasts used by an imported file that no target declares
+///
+/// Libraries can't declare broadcasts themselves, but as broadcasts are global they may use those of any target.
+/// `declared` are the broadcasts declared by the headers of all targets.
+pub fn check_library_broadcasts(usage: &BroadcastUsage, declared: &[&Ident]) -> Diagnostics {
+    let mut diagnostics = Diagnostics::default();
+    for (name, span) in usage.sent.iter().chain(&usage.received) {
+        if !declared.contains(&name) {
+            diagnostics.errors.push(AnalysisError::UndeclaredSymbol {
+                kind: SymbolKind::Broadcast,
+                name: name.to_string(),
+                span: *span,
+            });
+        }
+    }
+    diagnostics
+}
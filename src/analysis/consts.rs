@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{AnalysisError, Diagnostics, SymbolKind, SymbolTable};
+use crate::catalog::Type;
+use crate::parsing::{
+    Arg, BinOp, BlockCall, Expr, ExprKind, Headers, Ident, ScrFile, ScriptKind, Segment, Span,
+    Stmt, StmtKind,
+};
+use scratch_sb3::{Number, Value};
+
+/// Evaluate the constants declared in `headers` and define them in `symbols`
+pub(super) fn evaluate(
+    headers: &Headers,
+    symbols: &mut SymbolTable,
+    diagnostics: &mut Diagnostics,
+) {
+    let mut evaluator = Evaluator {
+        headers,
+        symbols,
+        values: HashMap::new(),
+        pending: HashSet::new(),
+        diagnostics,
+    };
+
+    let mut decls: Vec<_> = headers.consts.iter().collect();
+    decls.sort_by_key(|(_, (_, span))| span.start);
+    for (name, (_, span)) in decls {
+        evaluator.constant(name, *span);
+    }
+
+    let values = evaluator.values;
+    for (name, value) in values {
+        if let Some(value) = value {
+            symbols.define_constant(name, value);
+        }
+    }
+}
+
+/// Replace all uses of constants in the scripts of `file` with their values
+///
+/// Parameters of custom blocks take precedence over constants of the same name.
+pub fn inline_constants(file: &mut ScrFile, symbols: &SymbolTable) {
+    for script in &mut file.scripts {
+        let params: HashSet<Ident> = match &script.kind {
+            ScriptKind::Def(signature) => signature.params().map(|p| p.name.clone()).collect(),
+            ScriptKind::When(_) => HashSet::new(),
+        };
+
+        let inliner = Inliner { symbols, params };
+        if let ScriptKind::When(hat) = &mut script.kind {
+            inliner.call(hat);
+        }
+        inliner.body(&mut script.body);
+    }
+}
+
+struct Evaluator<'a, 'd> {
+    headers: &'a Headers,
+    symbols: &'a SymbolTable,
+
+    /// Already evaluated constants, `None` if evaluation failed
+    values: HashMap<Ident, Option<Value>>,
+
+    /// Constants that are currently being evaluated, used to detect cycles
+    pending: HashSet<Ident>,
+    diagnostics: &'d mut Diagnostics,
+}
+
+impl Evaluator<'_, '_> {
+    fn constant(&mut self, name: &Ident, span: Span) -> Option<Value> {
+        if let Some(value) = self.values.get(name) {
+            return value.clone();
+        }
+
+        let Some((expr, _)) = self.headers.consts.get(name) else {
+            // constants of the stage are already evaluated
+            return match self.symbols.constant(name) {
+                Some(value) => Some(value.clone()),
+                None => {
+                    self.diagnostics
+                        .errors
+                        .push(AnalysisError::UndeclaredSymbol {
+                            kind: SymbolKind::Constant,
+                            name: name.to_string(),
+                            span,
+                        });
+                    None
+                }
+            };
+        };
+
+        if !self.pending.insert(name.clone()) {
+            self.diagnostics.errors.push(AnalysisError::ConstantCycle {
+                name: name.to_string(),
+                span,
+            });
+            return None;
+        }
+
+        let value = self.expr(expr);
+        self.pending.remove(name);
+        self.values.insert(name.clone(), value.clone());
+        value
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Option<Value> {
+        match &expr.kind {
+            ExprKind::Literal(value) => Some(value.clone()),
+            ExprKind::Ident(name) => self.constant(name, expr.span),
+            ExprKind::Binary(
+                op @ (BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div),
+                lhs,
+                rhs,
+            ) => {
+                let lhs = self.number(lhs);
+                let rhs = self.number(rhs);
+                let (lhs, rhs) = (lhs?, rhs?);
+
+                let result = arithmetic(*op, lhs, rhs);
+                if result.is_none() {
+                    let span = expr.span;
+                    self.diagnostics
+                        .errors
+                        .push(if *op == BinOp::Div && f32::from(rhs) == 0.0 {
+                            AnalysisError::NotFinite { span }
+                        } else {
+                            AnalysisError::NotExact { span }
+                        });
+                }
+                result.map(Value::Number)
+            }
+            ExprKind::Binary(..) | ExprKind::Call(_) => {
+                self.diagnostics
+                    .errors
+                    .push(AnalysisError::NotConstant { span: expr.span });
+                None
+            }
+        }
+    }
+
+    fn number(&mut self, expr: &Expr) -> Option<Number> {
+        match self.expr(expr)? {
+            Value::Number(num) => Some(num),
+            Value::String(string) => {
+                let num = string
+                    .trim()
+                    .parse::<i32>()
+                    .map(Number::Integer)
+                    .or_else(|_| string.trim().parse::<f32>().map(Number::Float));
+
+                if num.is_err() {
+                    self.diagnostics.errors.push(AnalysisError::TypeMismatch {
+                        expected: Type::Number,
+                        found: Type::String,
+                        span: expr.span,
+                    });
+                }
+                num.ok()
+            }
+        }
+    }
+}
+
+/// Apply an arithmetic operator, `None` unless the result is exact
+///
+/// Scratch computes with 64-bit floats, so only integers whose result is an integer in range are folded,
+/// anything else could end up differently than at runtime.
+pub(crate) fn arithmetic(op: BinOp, lhs: Number, rhs: Number) -> Option<Number> {
+    let (Number::Integer(a), Number::Integer(b)) = (lhs, rhs) else {
+        return None;
+    };
+
+    let result = match op {
+        BinOp::Add => a.checked_add(b),
+        BinOp::Sub => a.checked_sub(b),
+        BinOp::Mul => a.checked_mul(b),
+        BinOp::Div => a.checked_rem(b).filter(|r| *r == 0).map(|_| a / b),
+        _ => unreachable!("not an arithmetic operator"),
+    };
+    result.map(Number::Integer)
+}
+
+struct Inliner<'a> {
+    symbols: &'a SymbolTable,
+    params: HashSet<Ident>,
+}
+
+impl Inliner<'_> {
+    fn body(&self, body: &mut [Stmt]) {
+        for stmt in body {
+            match &mut stmt.kind {
+                StmtKind::Call(call) => self.call(call),
+                StmtKind::If {
+                    cond,
+                    then,
+                    otherwise,
+                } => {
+                    self.expr(cond);
+                    self.body(then);
+                    if let Some(otherwise) = otherwise {
+                        self.body(otherwise);
+                    }
+                }
+                StmtKind::Repeat { times, body } => {
+                    self.expr(times);
+                    self.body(body);
+                }
+                StmtKind::RepeatUntil { cond, body } | StmtKind::While { cond, body } => {
+                    self.expr(cond);
+                    self.body(body);
+                }
+                StmtKind::Forever(body) => self.body(body),
+                StmtKind::Match {
+                    value,
+                    arms,
+                    default,
+                } => {
+                    self.expr(value);
+                    for arm in arms {
+                        arm.patterns
+                            .iter_mut()
+                            .for_each(|pattern| self.expr(pattern));
+                        self.body(&mut arm.body);
+                    }
+                    if let Some(default) = default {
+                        self.body(default);
+                    }
+                }
+                StmtKind::For { from, to, body, .. } => {
+                    self.expr(from);
+                    self.expr(to);
+                    self.body(body);
+                }
+            }
+        }
+    }
+
+    fn call(&self, call: &mut BlockCall) {
+        for segment in &mut call.segments {
+            if let Segment::Arg(Arg::Reporter(expr) | Arg::Boolean(expr)) = segment {
+                self.expr(expr);
+            }
+        }
+    }
+
+    fn expr(&self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::Ident(name) if !self.params.contains(name) => {
+                if let Some(value) = self.symbols.constant(name) {
+                    expr.kind = ExprKind::Literal(value.clone());
+                }
+            }
+            ExprKind::Call(call) => self.call(call),
+            ExprKind::Binary(_, lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            _ => {}
+        }
+    }
+}
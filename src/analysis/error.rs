@@ -0,0 +1,260 @@
+use miette::Diagnostic;
+use thiserror::Error;
+
+use super::SymbolKind;
+use crate::catalog::{Shape, Type};
+use crate::parsing::Span;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum AnalysisError {
+    #[error("Undeclared identifier '{name}'")]
+    Undeclared {
+        name: String,
+
+        #[label("not found in this scope")]
+        span: Span,
+    },
+
+    #[error("Undeclared {kind} '{name}'")]
+    UndeclaredSymbol {
+        kind: SymbolKind,
+        name: String,
+
+        #[label("not found in this scope")]
+        span: Span,
+    },
+
+    #[error("'{pattern}' never starts a script in the {target}")]
+    WrongTarget {
+        pattern: String,
+        target: &'static str,
+
+        #[help]
+        help: &'static str,
+
+        #[label("never runs")]
+        span: Span,
+    },
+
+    #[error("Invalid option '{value}'")]
+    InvalidOption {
+        value: String,
+
+        #[help]
+        help: Option<String>,
+
+        #[label("not one of the valid options")]
+        span: Span,
+    },
+
+    #[error("A global {kind} named '{name}' already exists")]
+    #[diagnostic(help("the stage already declares it, rename one of them"))]
+    CollidesWithGlobal {
+        name: String,
+        kind: SymbolKind,
+
+        #[label("declared here")]
+        span: Span,
+    },
+
+    #[error("Cloud variable '{name}' declared in a sprite")]
+    #[diagnostic(help("cloud variables can only be declared by the stage"))]
+    CloudVariableInSprite {
+        name: String,
+
+        #[label("declared here")]
+        span: Span,
+    },
+
+    #[error("Sprite settings in the stage")]
+    #[diagnostic(help("the `sprite` header can only be used by sprites"))]
+    SpriteSettingsInStage {
+        #[label("declared here")]
+        span: Span,
+    },
+
+    #[error("'{name}' is declared as both a constant and a {other}")]
+    NameTaken {
+        name: String,
+        other: SymbolKind,
+
+        #[label("declared here")]
+        span: Span,
+    },
+
+    #[error("Constant '{name}' depends on itself")]
+    ConstantCycle {
+        name: String,
+
+        #[label("used in its own definition")]
+        span: Span,
+    },
+
+    #[error("Expression can't be evaluated at compile time")]
+    #[diagnostic(help("constants can only use literals, other constants and arithmetic"))]
+    NotConstant {
+        #[label("not constant")]
+        span: Span,
+    },
+
+    #[error("Constant arithmetic doesn't produce a finite number")]
+    NotFinite {
+        #[label("evaluates to infinity or NaN")]
+        span: Span,
+    },
+
+    #[error("Constant arithmetic can't be evaluated exactly")]
+    #[diagnostic(help(
+        "only integer arithmetic with an integer result is evaluated, as Scratch computes with 64-bit floats"
+    ))]
+    NotExact {
+        #[label("not an integer that fits into 32 bits")]
+        span: Span,
+    },
+
+    #[error("Unknown block '{name}'")]
+    UnknownBlock {
+        name: String,
+
+        #[help]
+        help: Option<String>,
+
+        #[label("no such block")]
+        span: Span,
+    },
+
+    #[error("Expected a {expected} but '{name}' is a {shape}")]
+    WrongShape {
+        name: String,
+        shape: Shape,
+        expected: &'static str,
+
+        #[label("cannot be used here")]
+        span: Span,
+    },
+
+    #[error("Expected a {expected} but found a {found}")]
+    TypeMismatch {
+        expected: Type,
+        found: Type,
+
+        #[label("this is a {found}")]
+        span: Span,
+    },
+
+    #[error("Unknown custom block '{pattern}'")]
+    UnknownProcedure {
+        pattern: String,
+
+        #[label("no matching definition")]
+        span: Span,
+    },
+
+    #[error("Custom block '{pattern}' is defined twice")]
+    DuplicateProcedure {
+        pattern: String,
+
+        #[label("redefined here")]
+        span: Span,
+
+        #[label("first defined here")]
+        first: Span,
+    },
+
+    #[error("Custom block '{pattern}' is already imported")]
+    AlreadyImported {
+        pattern: String,
+
+        #[label("defined again here")]
+        span: Span,
+
+        #[label("already imported here")]
+        import: Span,
+    },
+
+    #[error("Imported files can't declare a {kind} like '{name}'")]
+    #[diagnostic(help("only custom blocks can be shared between targets"))]
+    DeclarationInLibrary {
+        name: String,
+        kind: SymbolKind,
+
+        #[label("declared here")]
+        span: Span,
+    },
+
+    #[error("Imported files can't contain scripts with hat blocks")]
+    #[diagnostic(help("only custom blocks can be shared between targets"))]
+    HatInLibrary {
+        #[label("this hat block")]
+        span: Span,
+    },
+
+    #[error("Statement is never reached")]
+    Unreachable {
+        #[label("unreachable")]
+        span: Span,
+
+        #[label("the script never gets past this")]
+        end: Span,
+    },
+
+    #[error("Parameter '{name}' is declared twice")]
+    DuplicateParam {
+        name: String,
+
+        #[label("here")]
+        span: Span,
+    },
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[diagnostic(severity(Warning))]
+pub enum AnalysisWarning {
+    #[error("'{name}' shadows the global {kind} of the same name")]
+    ShadowsGlobal {
+        name: String,
+        kind: SymbolKind,
+
+        #[label("declared here")]
+        span: Span,
+    },
+
+    #[error("Parameter '{name}' shadows the {kind} of the same name")]
+    ParamShadows {
+        name: String,
+        kind: SymbolKind,
+
+        #[label("declared here")]
+        span: Span,
+    },
+
+    #[error("Broadcast '{name}' is sent but never received")]
+    NeverReceived {
+        name: String,
+
+        #[label("no target receives it")]
+        span: Span,
+    },
+
+    #[error("Broadcast '{name}' is received but never sent")]
+    NeverSent {
+        name: String,
+
+        #[label("no target sends it")]
+        span: Span,
+    },
+
+    #[error("Only clones can be deleted, but this script doesn't run in a clone")]
+    #[diagnostic(help("start the script with `when control::cloned`"))]
+    DeleteOutsideClone {
+        #[label("does nothing in the original sprite")]
+        span: Span,
+    },
+
+    #[error("The loop never runs, as the range counts down")]
+    #[diagnostic(help("ranges only count up and include both ends, like `1..10`"))]
+    EmptyRange {
+        #[label("empty range")]
+        span: Span,
+    },
+}
@@ -0,0 +1,834 @@
+mod broadcasts;
+mod consts;
+mod error;
+mod resolver;
+mod symbols;
+mod typeck;
+
+pub use broadcasts::{BroadcastUsage, check_broadcasts, check_library_broadcasts};
+pub(crate) use consts::arithmetic;
+pub use consts::inline_constants;
+pub use error::{AnalysisError, AnalysisWarning};
+pub use symbols::{Scope, Symbol, SymbolKind, SymbolTable};
+
+use crate::parsing::{Ident, Import, ScrFile, ScriptKind};
+
+/// Errors and warnings collected during semantic analysis
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub errors: Vec<AnalysisError>,
+    pub warnings: Vec<AnalysisWarning>,
+}
+
+/// Resolve all identifiers of a parsed `.scr` file and check the types of its expressions
+///
+/// `stage` is the symbol table of the stage if `file` belongs to a sprite, `None` if it is the stage itself.
+/// `imports` are the already analyzed files `file` imports, each next to its import statement.
+/// `indirect` are the files those import in turn, directly or not, each next to the import of `file` they come from.
+/// `sprites` are the names of all sprites in the project.
+pub fn analyze(
+    file: &ScrFile,
+    stage: Option<&SymbolTable>,
+    imports: &[(&Import, &ScrFile)],
+    indirect: &[(&Import, &ScrFile)],
+    sprites: &[Ident],
+) -> (SymbolTable, Diagnostics) {
+    let mut diagnostics = Diagnostics::default();
+
+    let mut symbols = match stage {
+        None => {
+            if let Some(settings) = &file.headers.sprite {
+                diagnostics
+                    .errors
+                    .push(AnalysisError::SpriteSettingsInStage {
+                        span: settings.span,
+                    });
+            }
+            SymbolTable::stage(&file.headers)
+        }
+        Some(stage) => {
+            for (name, decl) in file.headers.vars.iter().filter(|(_, decl)| decl.cloud) {
+                diagnostics
+                    .errors
+                    .push(AnalysisError::CloudVariableInSprite {
+                        name: name.to_string(),
+                        span: decl.span,
+                    });
+            }
+
+            let symbols = SymbolTable::sprite(&file.headers, stage);
+            for symbol in symbols.iter().filter(|s| s.scope == Scope::Local) {
+                // all targets share the same broadcasts, declaring one again changes nothing
+                if !symbol.kind.is_shared()
+                    || stage.get(symbol.kind, &symbol.name).is_none()
+                    || symbol.kind == SymbolKind::Broadcast
+                {
+                    continue;
+                }
+
+                // Scratch refuses local variables and lists named like global ones
+                if matches!(symbol.kind, SymbolKind::Variable | SymbolKind::List) {
+                    diagnostics.errors.push(AnalysisError::CollidesWithGlobal {
+                        name: symbol.name.to_string(),
+                        kind: symbol.kind,
+                        span: symbol.span,
+                    });
+                } else {
+                    diagnostics.warnings.push(AnalysisWarning::ShadowsGlobal {
+                        name: symbol.name.to_string(),
+                        kind: symbol.kind,
+                        span: symbol.span,
+                    });
+                }
+            }
+            symbols
+        }
+    };
+    symbols.declare_sprites(sprites);
+
+    for constant in symbols.iter().filter(|s| s.kind == SymbolKind::Constant) {
+        for other in [SymbolKind::Variable, SymbolKind::List] {
+            let Some(symbol) = symbols.get(other, &constant.name) else {
+                continue;
+            };
+
+            // blame the declaration in this file
+            let span = match symbol.scope {
+                Scope::Local => symbol.span,
+                Scope::Global if constant.scope == Scope::Local || stage.is_none() => constant.span,
+                Scope::Global => continue,
+            };
+            diagnostics.errors.push(AnalysisError::NameTaken {
+                name: constant.name.to_string(),
+                other,
+                span,
+            });
+        }
+    }
+
+    consts::evaluate(&file.headers, &mut symbols, &mut diagnostics);
+
+    let procedures = resolver::resolve(
+        &file.scripts,
+        imports,
+        indirect,
+        &symbols,
+        Some(stage.is_none()),
+        &mut diagnostics,
+    );
+    typeck::check(&file.scripts, &symbols, &procedures, &mut diagnostics);
+
+    (symbols, diagnostics)
+}
+
+/// Analyze a file that is imported by others
+///
+/// Such a file may only define custom blocks, which can't refer to any variables or other symbols.
+/// The broadcasts it uses are global though, see [`check_library_broadcasts`].
+///
+/// `imports` and `indirect` are the files it imports like for [`analyze`].
+pub fn analyze_library(
+    file: &ScrFile,
+    imports: &[(&Import, &ScrFile)],
+    indirect: &[(&Import, &ScrFile)],
+) -> Diagnostics {
+    let mut diagnostics = Diagnostics::default();
+
+    let mut declarations: Vec<_> = SymbolTable::stage(&file.headers).iter().cloned().collect();
+    declarations.sort_by_key(|symbol| symbol.span.start);
+    for symbol in declarations {
+        diagnostics
+            .errors
+            .push(AnalysisError::DeclarationInLibrary {
+                name: symbol.name.to_string(),
+                kind: symbol.kind,
+                span: symbol.span,
+            });
+    }
+
+    for script in &file.scripts {
+        if let ScriptKind::When(hat) = &script.kind {
+            diagnostics
+                .errors
+                .push(AnalysisError::HatInLibrary { span: hat.span });
+        }
+    }
+
+    let mut symbols = SymbolTable::default();
+    symbols.declare_broadcasts(&BroadcastUsage::new(&file.scripts));
+    let procedures = resolver::resolve(
+        &file.scripts,
+        imports,
+        indirect,
+        &symbols,
+        None,
+        &mut diagnostics,
+    );
+    typeck::check(&file.scripts, &symbols, &procedures, &mut diagnostics);
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Type;
+    use crate::parsing::{self, Arg, ExprKind, Ident, Script, StmtKind};
+    use scratch_sb3::target::{Language, RotationStyle, VideoState};
+    use scratch_sb3::{Number, Value};
+
+    fn analyze_source(source: &str, stage: Option<&SymbolTable>) -> (SymbolTable, Diagnostics) {
+        let file = parsing::parse(source).expect("source should parse");
+        analyze(&file, stage, &[], &[], &[])
+    }
+
+    #[test]
+    fn undeclared_identifiers() {
+        let (_, diagnostics) = analyze_source(
+            r#"
+            vars { fun = 99; }
+            sounds { sound1: WAV; }
+
+            def greet_(name) {
+                looks::say_(name);
+            }
+
+            when events::flag {
+                greet_(fun);
+                sound::play_sound_[sound1]_until_done;
+                sound::play_sound_[sound2]_until_done;
+                looks::say_(nope);
+                gret_(1);
+            }
+            "#,
+            None,
+        );
+
+        let names: Vec<String> = diagnostics
+            .errors
+            .iter()
+            .map(|err| match err {
+                AnalysisError::Undeclared { name, .. }
+                | AnalysisError::UndeclaredSymbol { name, .. } => name.clone(),
+                AnalysisError::UnknownProcedure { pattern, .. } => pattern.clone(),
+                err => panic!("unexpected error: {err}"),
+            })
+            .collect();
+
+        assert_eq!(names, vec!["sound2", "nope", "gret_()"]);
+        assert!(diagnostics.warnings.is_empty());
+    }
+
+    #[test]
+    fn type_mismatches() {
+        let (_, diagnostics) = analyze_source(
+            r#"
+            vars { fun = 99; }
+
+            def wait_if_<cond> {
+                control::wait_until_<cond>;
+            }
+
+            when events::flag {
+                repeat (<fun greater 50>) {
+                    motion::move_("ten")_steps;
+                }
+                if <"yes"> {
+                    looks::say_(<fun = 1>);
+                    motion::move_("10")_steps;
+                }
+                wait_if_<fun>;
+                looks::say_(motion::x_position);
+                motion::move_(looks::say_(1))_steps;
+            }
+            "#,
+            None,
+        );
+
+        let mismatches: Vec<(Type, Type)> = diagnostics
+            .errors
+            .iter()
+            .filter_map(|err| match err {
+                AnalysisError::TypeMismatch {
+                    expected, found, ..
+                } => Some((*expected, *found)),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            mismatches,
+            vec![
+                (Type::Number, Type::Boolean),
+                (Type::Number, Type::String),
+                (Type::Boolean, Type::String),
+                (Type::Boolean, Type::Any),
+            ]
+        );
+        assert!(matches!(
+            diagnostics.errors.last(),
+            Some(AnalysisError::WrongShape { .. })
+        ));
+        assert_eq!(diagnostics.errors.len(), 5);
+    }
+
+    #[test]
+    fn sprite_scoping() {
+        let (stage, diagnostics) = analyze_source(
+            "vars { score; lives; } costumes { backdrop1: SVG; } sounds { pop: WAV; }",
+            None,
+        );
+        assert!(diagnostics.errors.is_empty());
+
+        let (sprite, diagnostics) = analyze_source(
+            r#"
+            vars { lives = 3; speed; }
+            sounds { pop: WAV; }
+            when events::flag {
+                looks::say_(score + lives + speed);
+                sound::play_sound_[pop]_until_done;
+            }
+            "#,
+            Some(&stage),
+        );
+
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::CollidesWithGlobal { name, .. }] if name == "lives"
+        ));
+        assert!(diagnostics.warnings.is_empty());
+
+        // sprites can't play the sounds of the stage
+        let (_, diagnostics) = analyze_source(
+            "when events::flag { sound::play_sound_[pop]_until_done; }",
+            Some(&stage),
+        );
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::UndeclaredSymbol { name, kind: SymbolKind::Sound, .. }] if name == "pop"
+        ));
+
+        let lives = Ident::new("lives".to_string());
+        let speed = Ident::new("speed".to_string());
+        let score = Ident::new("score".to_string());
+        let backdrop = Ident::new("backdrop1".to_string());
+        assert_eq!(
+            sprite.get(SymbolKind::Variable, &lives).unwrap().scope,
+            Scope::Local
+        );
+        assert_eq!(
+            sprite.get(SymbolKind::Variable, &speed).unwrap().scope,
+            Scope::Local
+        );
+        assert_eq!(
+            sprite.get(SymbolKind::Variable, &score).unwrap().scope,
+            Scope::Global
+        );
+        assert!(sprite.get(SymbolKind::Backdrop, &backdrop).is_some());
+    }
+
+    #[test]
+    fn cloud_variables() {
+        let (stage, diagnostics) = analyze_source("vars { cloud highscore = 0; }", None);
+        assert!(diagnostics.errors.is_empty());
+
+        let (_, diagnostics) = analyze_source("vars { cloud coins; }", Some(&stage));
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::CloudVariableInSprite { name, .. }] if name == "coins"
+        ));
+
+        assert!(parsing::parse(r#"vars { cloud name = "joe"; }"#).is_err());
+
+        let vars: String = (0..11).map(|i| format!("cloud v{i};")).collect();
+        assert!(parsing::parse(&format!("vars {{ {vars} }}")).is_err());
+    }
+
+    #[test]
+    fn stage_settings() {
+        let source = r#"set { tempo = 120; volume = 0; videoState = "off"; textToSpeechLanguage = "pt-br"; }"#;
+        let settings = parsing::parse(source).unwrap().headers.set;
+        assert_eq!(settings.tempo, Some(120));
+        assert_eq!(settings.volume, Some(0));
+        assert_eq!(settings.video_transparency, None);
+        assert_eq!(settings.video_state, Some(VideoState::Off));
+        assert_eq!(
+            settings.text_to_speech_language,
+            Some(Language::PortugueseBr)
+        );
+
+        for invalid in [
+            r#"set { tempo = "fast"; }"#,
+            "set { tempo = 10; }",
+            "set { volume = 101; }",
+            "set { videoTransparency = -1; }",
+            r#"set { videoState = "sideways"; }"#,
+            r#"set { textToSpeechLanguage = "Klingon"; }"#,
+            "set { textToSpeechLanguage = 1; }",
+            "set { x = 1; }",
+            "set { tempo = 60; tempo = 70; }",
+        ] {
+            assert!(parsing::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn sprite_settings() {
+        let source = r#"sprite { x = -120; size = 50; direction = -90; draggable = true; rotationStyle = "left-right"; }"#;
+        let file = parsing::parse(source).unwrap();
+        let settings = file.headers.sprite.as_ref().unwrap();
+        assert_eq!(settings.x, Some(Number::Integer(-120)));
+        assert_eq!(settings.y, None);
+        assert_eq!(settings.direction, Some(270));
+        assert_eq!(settings.rotation_style, Some(RotationStyle::LeftRight));
+
+        let (stage, diagnostics) = analyze_source("", None);
+        assert!(diagnostics.errors.is_empty());
+        let (_, diagnostics) = analyze_source(source, Some(&stage));
+        assert!(diagnostics.errors.is_empty());
+
+        let (_, diagnostics) = analyze_source(source, None);
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::SpriteSettingsInStage { .. }]
+        ));
+
+        for invalid in [
+            "sprite { size = -5; }",
+            "sprite { direction = 200; }",
+            r#"sprite { visible = "yes"; }"#,
+            r#"sprite { rotationStyle = "sideways"; }"#,
+            "sprite { tempo = 60; }",
+            "sprite { x = 1; x = 2; }",
+        ] {
+            assert!(parsing::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn imports() {
+        let library = parsing::parse("def double_(x) { looks::say_(x * 2); }").unwrap();
+        assert!(analyze_library(&library, &[], &[]).errors.is_empty());
+
+        let file = parsing::parse(
+            r#"
+            import "lib.scr";
+            def double_(y) { looks::say_(y); }
+            when events::flag { double_(4); }
+            "#,
+        )
+        .unwrap();
+        let imports = [(&file.imports[0], &library)];
+        let (_, diagnostics) = analyze(&file, None, &imports, &[], &[]);
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::AlreadyImported { pattern, .. }] if pattern == "double_()"
+        ));
+
+        // the custom blocks of a library's own imports end up in the same target
+        let middle = parsing::parse(r#"import "lib.scr"; def triple_(x) { double_(x); }"#).unwrap();
+        let middle_imports = [(&middle.imports[0], &library)];
+        assert!(
+            analyze_library(&middle, &middle_imports, &[])
+                .errors
+                .is_empty()
+        );
+
+        let file = parsing::parse(
+            r#"
+            import "middle.scr";
+            def double_(y) { looks::say_(y); }
+            when events::flag { triple_(4); }
+            "#,
+        )
+        .unwrap();
+        let imports = [(&file.imports[0], &middle)];
+        let indirect = [(&file.imports[0], &library)];
+        let (_, diagnostics) = analyze(&file, None, &imports, &indirect, &[]);
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::AlreadyImported { pattern, .. }] if pattern == "double_()"
+        ));
+
+        let stateful = parsing::parse("vars { x; } def inc { looks::say_(x); }").unwrap();
+        assert_eq!(analyze_library(&stateful, &[], &[]).errors.len(), 2);
+    }
+
+    #[test]
+    fn constants() {
+        let (stage, diagnostics) = analyze_source(
+            r#"
+            const { HALF = WIDTH / 2; WIDTH = 480; NAME = "cat"; }
+            when events::flag { looks::say_(HALF); }
+            "#,
+            None,
+        );
+        assert!(diagnostics.errors.is_empty());
+
+        let half = Ident::new("HALF".to_string());
+        assert_eq!(stage.constant(&half), Some(&Value::Number(240.into())));
+
+        let (_, diagnostics) = analyze_source(
+            r#"
+            const { A = B + 1; B = A; C = "x" * 2; D = 1 / 0; E = motion::x_position; F = 1 / 3; }
+            when events::flag { motion::move_(NAME)_steps; }
+            "#,
+            Some(&stage),
+        );
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [
+                AnalysisError::ConstantCycle { .. },
+                AnalysisError::TypeMismatch { .. },
+                AnalysisError::NotFinite { .. },
+                AnalysisError::NotConstant { .. },
+                AnalysisError::NotExact { .. },
+                AnalysisError::TypeMismatch { .. },
+            ]
+        ));
+
+        let mut file = parsing::parse(
+            r#"
+            def walk_(HALF) { motion::move_(HALF)_steps; }
+            when events::flag { motion::move_(HALF)_steps; }
+            "#,
+        )
+        .unwrap();
+        inline_constants(&mut file, &stage);
+
+        let step = |script: &Script| match &script.body[0].kind {
+            StmtKind::Call(call) => match call.args().next() {
+                Some(Arg::Reporter(expr)) => expr.kind.clone(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        assert!(matches!(step(&file.scripts[0]), ExprKind::Ident(_)));
+        assert!(matches!(
+            step(&file.scripts[1]),
+            ExprKind::Literal(Value::Number(Number::Integer(240)))
+        ));
+    }
+
+    #[test]
+    fn loops() {
+        let (_, diagnostics) = analyze_source(
+            r#"
+            vars { i; }
+            lists { items; }
+
+            when events::flag {
+                for i in 1..10 {
+                    while <i greater 5> { looks::say_(i); }
+                }
+                for items in 1.."ten" {}
+                for i in 10..1 {}
+                repeat until <i> {}
+                forever {
+                    looks::say_(i);
+                }
+                looks::say_(i);
+            }
+            "#,
+            None,
+        );
+
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [
+                AnalysisError::UndeclaredSymbol { name, kind: SymbolKind::Variable, .. },
+                AnalysisError::TypeMismatch { expected: Type::Number, found: Type::String, .. },
+                AnalysisError::TypeMismatch { expected: Type::Boolean, .. },
+                AnalysisError::Unreachable { .. },
+            ] if name == "items"
+        ));
+        assert!(matches!(
+            diagnostics.warnings.as_slice(),
+            [AnalysisWarning::EmptyRange { .. }]
+        ));
+    }
+
+    #[test]
+    fn event_hats() {
+        let (stage, diagnostics) = analyze_source(
+            r#"
+            costumes { night: SVG; }
+            broadcasts { start; }
+
+            when events::key_[space]_pressed {}
+            when events::key_[up_arrow]_pressed {}
+            when events::i_receive_[start] {}
+            when events::backdrop_switches_to_[night] {}
+            when events::[timer]_exceeds_(10) {}
+            "#,
+            None,
+        );
+        assert!(diagnostics.errors.is_empty(), "{:?}", diagnostics.errors);
+
+        let (_, diagnostics) = analyze_source(
+            r#"
+            costumes { cat: SVG; }
+
+            when events::this_sprite_clicked {}
+            when control::start_as_clone {}
+            when events::backdrop_switches_to_[night] {}
+            when events::backdrop_switches_to_[cat] {}
+            when events::i_receive_[stop] {}
+            when events::key_[enter]_pressed {}
+            when makeymakey::[up_down]_pressed_in_order {}
+            "#,
+            Some(&stage),
+        );
+
+        let invalid: Vec<(Option<SymbolKind>, &str)> = diagnostics
+            .errors
+            .iter()
+            .map(|err| match err {
+                AnalysisError::UndeclaredSymbol { kind, name, .. } => (Some(*kind), name.as_str()),
+                AnalysisError::InvalidOption { value, .. } => (None, value.as_str()),
+                _ => panic!("unexpected error {err:?}"),
+            })
+            .collect();
+        assert_eq!(
+            invalid,
+            [
+                (Some(SymbolKind::Backdrop), "cat"),
+                (Some(SymbolKind::Broadcast), "stop"),
+                (None, "enter"),
+            ]
+        );
+
+        // only sprites can be clicked, and clicking the stage doesn't start their scripts
+        let (_, diagnostics) = analyze_source("when events::this_sprite_clicked {}", None);
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::WrongTarget {
+                target: "stage",
+                ..
+            }]
+        ));
+        let (_, diagnostics) = analyze_source("when events::stage_clicked {}", Some(&stage));
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::WrongTarget {
+                target: "sprite",
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn broadcast_usage() {
+        let stage = parsing::parse(
+            r#"
+            broadcasts { start; stop; }
+
+            when events::flag {
+                events::broadcast_[start];
+                events::broadcast_["stop"]_and_wait;
+            }
+            "#,
+        )
+        .expect("source should parse");
+        let sprite = parsing::parse(
+            r#"
+            when events::i_receive_[start] {}
+            when events::i_receive_[reset] {}
+            "#,
+        )
+        .expect("source should parse");
+
+        let stage = BroadcastUsage::new(&stage.scripts);
+        let sprite = BroadcastUsage::new(&sprite.scripts);
+        let names: Vec<&str> = stage.names().map(Ident::as_str).collect();
+        assert_eq!(names, ["start", "stop"]);
+        let names: Vec<&str> = sprite.names().map(Ident::as_str).collect();
+        assert_eq!(names, ["start", "reset"]);
+
+        let warnings: Vec<Vec<String>> = check_broadcasts(&[&stage, &sprite])
+            .into_iter()
+            .map(|diagnostics| {
+                diagnostics
+                    .warnings
+                    .iter()
+                    .map(|warning| warning.to_string())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                ["Broadcast 'stop' is sent but never received"],
+                ["Broadcast 'reset' is received but never sent"],
+            ]
+        );
+    }
+
+    #[test]
+    fn library_broadcasts() {
+        let library = parsing::parse("def start { events::broadcast_[go]; }").unwrap();
+        let diagnostics = analyze_library(&library, &[], &[]);
+        assert!(diagnostics.errors.is_empty(), "{:?}", diagnostics.errors);
+
+        let usage = BroadcastUsage::new(&library.scripts);
+        let go = Ident::new("go".to_string());
+        assert!(check_library_broadcasts(&usage, &[&go]).errors.is_empty());
+
+        // broadcasts declared by sprites are global as well
+        let sprite = parsing::parse("broadcasts { go; }").unwrap();
+        let declared: Vec<_> = sprite.headers.broadcasts.keys().collect();
+        assert!(
+            check_library_broadcasts(&usage, &declared)
+                .errors
+                .is_empty()
+        );
+
+        assert!(matches!(
+            check_library_broadcasts(&usage, &[]).errors.as_slice(),
+            [AnalysisError::UndeclaredSymbol { kind: SymbolKind::Broadcast, name, .. }] if name == "go"
+        ));
+
+        let sprite = parsing::parse("when events::i_receive_[go] {}").unwrap();
+        let sprite = BroadcastUsage::new(&sprite.scripts);
+        let diagnostics = check_broadcasts(&[&usage, &sprite]);
+        assert!(diagnostics.iter().all(|d| d.warnings.is_empty()));
+    }
+
+    #[test]
+    fn clones() {
+        let file = parsing::parse(
+            r#"
+            when events::flag {
+                control::create_clone_of_[myself];
+                control::create_clone_of_["My Cat"];
+                control::create_clone_of_[dog];
+                control::delete_this_clone;
+            }
+
+            when control::cloned {
+                control::delete_this_clone;
+            }
+
+            def vanish {
+                control::delete_this_clone;
+            }
+            "#,
+        )
+        .expect("source should parse");
+        let sprites = [Ident::new("My Cat".to_string())];
+        let (stage, _) = analyze_source("", None);
+        let (_, diagnostics) = analyze(&file, Some(&stage), &[], &[], &sprites);
+
+        let invalid: Vec<&str> = diagnostics
+            .errors
+            .iter()
+            .map(|err| match err {
+                AnalysisError::InvalidOption { value, .. } => value.as_str(),
+                _ => panic!("unexpected error {err:?}"),
+            })
+            .collect();
+        assert_eq!(invalid, ["dog"]);
+
+        assert_eq!(diagnostics.warnings.len(), 1);
+        assert!(matches!(
+            diagnostics.warnings[0],
+            AnalysisWarning::DeleteOutsideClone { .. }
+        ));
+
+        // the stage can't be cloned
+        let (_, diagnostics) = analyze_source(
+            r#"
+            when events::flag { control::create_clone_of_[myself]; }
+            when control::cloned {}
+            "#,
+            None,
+        );
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [
+                AnalysisError::InvalidOption { value, .. },
+                AnalysisError::WrongTarget { target: "stage", .. },
+            ] if value == "myself"
+        ));
+    }
+
+    #[test]
+    fn stop() {
+        let source = r#"
+            when events::flag {
+                control::stop_[other_scripts_in_sprite];
+                if <sensing::mouse_down> {
+                    control::stop_["this script"];
+                }
+                control::stop_[all];
+                looks::say_("never");
+            }
+            "#;
+        let (stage, _) = analyze_source("", None);
+        let (_, diagnostics) = analyze_source(source, Some(&stage));
+
+        let [AnalysisError::Unreachable { span, end }] = diagnostics.errors.as_slice() else {
+            panic!("unexpected errors {:?}", diagnostics.errors);
+        };
+        assert_eq!(&source[end.start..end.end], "control::stop_[all];");
+        assert_eq!(&source[span.start..span.end], "looks::say_(\"never\");");
+
+        // each target can only stop its own other scripts
+        let (_, diagnostics) = analyze_source(source, None);
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::InvalidOption { value, .. }, AnalysisError::Unreachable { .. }]
+                if value == "other_scripts_in_sprite"
+        ));
+        let (_, diagnostics) = analyze_source(
+            r#"when events::flag { control::stop_["other scripts in stage"]; }"#,
+            Some(&stage),
+        );
+        assert!(matches!(
+            diagnostics.errors.as_slice(),
+            [AnalysisError::InvalidOption { value, .. }] if value == "other scripts in stage"
+        ));
+        let (_, diagnostics) = analyze_source(
+            "when events::flag { control::stop_[other_scripts_in_stage]; }",
+            None,
+        );
+        assert!(diagnostics.errors.is_empty());
+    }
+
+    #[test]
+    fn list_methods() {
+        let (_, diagnostics) = analyze_source(
+            r#"
+            lists { items; }
+
+            when events::flag {
+                items.len();
+                looks::say_(items.push(1));
+                nope.clear();
+                repeat (items[1]) {}
+            }
+            "#,
+            None,
+        );
+
+        assert!(
+            matches!(
+                diagnostics.errors.as_slice(),
+                [
+                    AnalysisError::UndeclaredSymbol { kind: SymbolKind::List, name, .. },
+                    AnalysisError::WrongShape { expected: "statement", .. },
+                    AnalysisError::WrongShape { expected: "reporter", .. },
+                ] if name == "nope"
+            ),
+            "{:?}",
+            diagnostics.errors
+        );
+
+        for source in [
+            "when events::flag { items.pop(); }",
+            "when events::flag { items.push(); }",
+        ] {
+            assert!(parsing::parse(source).is_err());
+        }
+    }
+}
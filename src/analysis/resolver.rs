@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+
+use super::{AnalysisError, AnalysisWarning, Diagnostics, SymbolKind, SymbolTable};
+use crate::catalog::{self, ArgSpec, FieldSpec};
+use crate::parsing::{
+    Arg, BlockCall, Expr, ExprKind, Field, FieldKind, Ident, Import, Param, ScrFile, Script,
+    ScriptKind, Signature, Span, Stmt, StmtKind,
+};
+
+/// Resolve all identifiers and return the custom blocks defined by `scripts` or imported
+///
+/// `indirect` are the files imported by the imported files, which can't be called but end up in the same target.
+/// `stage` tells whether the scripts belong to the stage, it is `None` for libraries.
+pub(super) fn resolve<'a>(
+    scripts: &'a [Script],
+    imports: &[(&Import, &'a ScrFile)],
+    indirect: &[(&Import, &ScrFile)],
+    symbols: &SymbolTable,
+    stage: Option<bool>,
+    diagnostics: &mut Diagnostics,
+) -> HashMap<String, &'a Signature> {
+    let mut procedures: HashMap<String, &Signature> = HashMap::new();
+    let mut imported: HashMap<String, Span> = HashMap::new();
+    for (import, file) in imports {
+        for script in &file.scripts {
+            let ScriptKind::Def(signature) = &script.kind else {
+                continue;
+            };
+
+            let pattern = signature.pattern();
+            if let Some(first) = imported.get(&pattern) {
+                diagnostics.errors.push(AnalysisError::AlreadyImported {
+                    pattern,
+                    span: import.span,
+                    import: *first,
+                });
+            } else {
+                imported.insert(pattern.clone(), import.span);
+                procedures.insert(pattern, signature);
+            }
+        }
+    }
+
+    // custom blocks of the same target can't share a pattern, even if they aren't callable from here
+    for (import, file) in indirect {
+        for script in &file.scripts {
+            let ScriptKind::Def(signature) = &script.kind else {
+                continue;
+            };
+
+            let pattern = signature.pattern();
+            if let Some(first) = imported.get(&pattern) {
+                diagnostics.errors.push(AnalysisError::AlreadyImported {
+                    pattern,
+                    span: import.span,
+                    import: *first,
+                });
+            } else {
+                imported.insert(pattern, import.span);
+            }
+        }
+    }
+
+    for script in scripts {
+        let ScriptKind::Def(signature) = &script.kind else {
+            continue;
+        };
+
+        let pattern = signature.pattern();
+        if let Some(import) = imported.get(&pattern) {
+            // blocks imported indirectly aren't callable, so calls refer to this definition
+            procedures.entry(pattern.clone()).or_insert(signature);
+            diagnostics.errors.push(AnalysisError::AlreadyImported {
+                pattern,
+                span: signature.span,
+                import: *import,
+            });
+        } else if let Some(first) = procedures.get(&pattern) {
+            diagnostics.errors.push(AnalysisError::DuplicateProcedure {
+                pattern,
+                span: signature.span,
+                first: first.span,
+            });
+        } else {
+            procedures.insert(pattern, signature);
+        }
+    }
+
+    for script in scripts {
+        let mut resolver = Resolver {
+            symbols,
+            stage,
+            procedures: &procedures,
+            params: HashMap::new(),
+            diagnostics,
+        };
+
+        match &script.kind {
+            ScriptKind::When(hat) => {
+                resolver.hat(hat);
+                resolver.call(hat);
+            }
+            ScriptKind::Def(signature) => resolver.declare_params(signature),
+        }
+
+        resolver.body(&script.body);
+    }
+
+    procedures
+}
+
+struct Resolver<'a, 'd> {
+    symbols: &'a SymbolTable,
+
+    /// Whether the scripts belong to the stage, unknown for libraries
+    stage: Option<bool>,
+    procedures: &'a HashMap<String, &'a Signature>,
+    params: HashMap<&'a Ident, &'a Param>,
+    diagnostics: &'d mut Diagnostics,
+}
+
+impl<'a> Resolver<'a, '_> {
+    fn declare_params(&mut self, signature: &'a Signature) {
+        for param in signature.params() {
+            if self.params.insert(&param.name, param).is_some() {
+                self.diagnostics.errors.push(AnalysisError::DuplicateParam {
+                    name: param.name.to_string(),
+                    span: param.span,
+                });
+            }
+
+            for kind in [SymbolKind::Variable, SymbolKind::Constant, SymbolKind::List] {
+                if self.symbols.get(kind, &param.name).is_some() {
+                    self.diagnostics
+                        .warnings
+                        .push(AnalysisWarning::ParamShadows {
+                            name: param.name.to_string(),
+                            kind,
+                            span: param.span,
+                        });
+                }
+            }
+        }
+    }
+
+    /// Report hat blocks that never start a script in this kind of target
+    fn hat(&mut self, hat: &BlockCall) {
+        let Some(spec) = hat
+            .namespace
+            .as_ref()
+            .and_then(|namespace| catalog::lookup(namespace.as_str(), &hat.pattern()))
+        else {
+            return;
+        };
+
+        let (target, help) = match (spec.opcode, self.stage) {
+            ("control_start_as_clone", Some(true)) => ("stage", "the stage can't be cloned"),
+            ("event_whenthisspriteclicked", Some(true)) => {
+                ("stage", "use `events::stage_clicked` in the stage")
+            }
+            ("event_whenstageclicked", Some(false)) => {
+                ("sprite", "use `events::this_sprite_clicked` in sprites")
+            }
+            _ => return,
+        };
+        self.diagnostics.errors.push(AnalysisError::WrongTarget {
+            pattern: hat.pattern(),
+            target,
+            help,
+            span: hat.span,
+        });
+    }
+
+    fn body(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Call(call) => {
+                if call.namespace.is_none() && !self.procedures.contains_key(&call.pattern()) {
+                    self.diagnostics
+                        .errors
+                        .push(AnalysisError::UnknownProcedure {
+                            pattern: call.pattern(),
+                            span: call.span,
+                        });
+                }
+                self.call(call);
+            }
+            StmtKind::If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                self.expr(cond);
+                self.body(then);
+                if let Some(otherwise) = otherwise {
+                    self.body(otherwise);
+                }
+            }
+            StmtKind::Repeat { times, body } => {
+                self.expr(times);
+                self.body(body);
+            }
+            StmtKind::RepeatUntil { cond, body } | StmtKind::While { cond, body } => {
+                self.expr(cond);
+                self.body(body);
+            }
+            StmtKind::Forever(body) => self.body(body),
+            StmtKind::Match {
+                value,
+                arms,
+                default,
+            } => {
+                self.expr(value);
+                for arm in arms {
+                    arm.patterns.iter().for_each(|pattern| self.expr(pattern));
+                    self.body(&arm.body);
+                }
+                if let Some(default) = default {
+                    self.body(default);
+                }
+            }
+            StmtKind::For {
+                var,
+                var_span,
+                from,
+                to,
+                body,
+            } => {
+                // the counter is a Scratch variable, so it has to be declared like one
+                if self.symbols.get(SymbolKind::Variable, var).is_none() {
+                    self.diagnostics
+                        .errors
+                        .push(AnalysisError::UndeclaredSymbol {
+                            kind: SymbolKind::Variable,
+                            name: var.to_string(),
+                            span: *var_span,
+                        });
+                }
+                self.expr(from);
+                self.expr(to);
+                self.body(body);
+            }
+        }
+    }
+
+    fn call(&mut self, call: &BlockCall) {
+        let spec = call
+            .namespace
+            .as_ref()
+            .and_then(|namespace| catalog::lookup(namespace.as_str(), &call.pattern()));
+
+        for (i, arg) in call.args().enumerate() {
+            match arg {
+                Arg::Reporter(expr) | Arg::Boolean(expr) => self.expr(expr),
+                Arg::Field(field) => {
+                    // fields of unknown blocks are meaningless, the block itself is reported
+                    let field_spec = match spec.map(|spec| &spec.args[i]) {
+                        Some(
+                            ArgSpec::Menu { field, .. }
+                            | ArgSpec::Field(field)
+                            | ArgSpec::Primitive(field),
+                        ) => field,
+                        _ => continue,
+                    };
+                    self.field(field, field_spec);
+                }
+            }
+        }
+    }
+
+    fn field(&mut self, field: &Field, spec: &FieldSpec) {
+        let (name, option) = match &field.kind {
+            FieldKind::Ident(name) => (name.as_str(), spec.option_for_ident(name.as_str())),
+            FieldKind::String(string) => (string.as_str(), spec.option_for_str(string)),
+        };
+
+        if let Some(option) = option {
+            // Scratch only offers to stop the other scripts of the target the block is in,
+            // and only sprites can clone themselves
+            let help = match (spec.name, option, self.stage) {
+                ("STOP_OPTION", "other scripts in sprite", Some(true)) => {
+                    "use \"other scripts in stage\" in this target"
+                }
+                ("STOP_OPTION", "other scripts in stage", Some(false)) => {
+                    "use \"other scripts in sprite\" in this target"
+                }
+                ("CLONE_OPTION", "_myself_", Some(true)) => {
+                    "the stage can't be cloned, name a sprite instead"
+                }
+                _ => return,
+            };
+            self.diagnostics.errors.push(AnalysisError::InvalidOption {
+                value: name.to_string(),
+                help: Some(help.to_string()),
+                span: field.span,
+            });
+            return;
+        }
+
+        let declared = spec.refers.is_some_and(|kind| {
+            self.symbols
+                .get(kind, &Ident::new(name.to_string()))
+                .is_some()
+        });
+
+        match (spec.refers, &field.kind) {
+            _ if declared => {}
+            (Some(kind), FieldKind::Ident(_)) if spec.options.is_empty() => self
+                .diagnostics
+                .errors
+                .push(AnalysisError::UndeclaredSymbol {
+                    kind,
+                    name: name.to_string(),
+                    span: field.span,
+                }),
+            _ => {
+                let mut expected: Vec<String> =
+                    spec.options.iter().map(|o| format!("\"{o}\"")).collect();
+                if let Some(kind) = spec.refers {
+                    expected.push(format!("a {kind}"));
+                }
+
+                self.diagnostics.errors.push(AnalysisError::InvalidOption {
+                    value: name.to_string(),
+                    help: Some(format!("expected {}", expected.join(", "))),
+                    span: field.span,
+                })
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(_) => {}
+            ExprKind::Ident(name) => {
+                let declared = self.params.contains_key(name)
+                    || self.symbols.get(SymbolKind::Constant, name).is_some()
+                    || self.symbols.get(SymbolKind::Variable, name).is_some()
+                    || self.symbols.get(SymbolKind::List, name).is_some();
+
+                if !declared {
+                    self.undeclared(name, expr.span);
+                }
+            }
+            ExprKind::Call(call) => self.call(call),
+            ExprKind::Binary(_, lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+        }
+    }
+
+    fn undeclared(&mut self, name: &Ident, span: Span) {
+        self.diagnostics.errors.push(AnalysisError::Undeclared {
+            name: name.to_string(),
+            span,
+        });
+    }
+}
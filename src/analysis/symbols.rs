@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::BroadcastUsage;
+use crate::parsing::{Headers, Ident, Span};
+use scratch_sb3::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Variable,
+    Constant,
+    List,
+    Broadcast,
+    Costume,
+    Backdrop,
+    Sound,
+    /// Declared by the manifest rather than a header
+    Sprite,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolKind::Variable => write!(f, "variable"),
+            SymbolKind::Constant => write!(f, "constant"),
+            SymbolKind::List => write!(f, "list"),
+            SymbolKind::Broadcast => write!(f, "broadcast"),
+            SymbolKind::Costume => write!(f, "costume"),
+            SymbolKind::Backdrop => write!(f, "backdrop"),
+            SymbolKind::Sound => write!(f, "sound"),
+            SymbolKind::Sprite => write!(f, "sprite"),
+        }
+    }
+}
+
+impl SymbolKind {
+    /// Whether global symbols of this kind are visible to sprites
+    ///
+    /// Sprites can't play the sounds of the stage, only its variables, constants, lists, broadcasts and backdrops are shared.
+    pub fn is_shared(self) -> bool {
+        matches!(
+            self,
+            SymbolKind::Variable
+                | SymbolKind::Constant
+                | SymbolKind::List
+                | SymbolKind::Broadcast
+                | SymbolKind::Backdrop
+        )
+    }
+}
+
+/// Where a symbol is visible
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Declared by the stage and visible to all targets if the kind [is shared](SymbolKind::is_shared)
+    Global,
+
+    /// Declared by a sprite and only visible to it
+    Local,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: Ident,
+    pub kind: SymbolKind,
+    pub scope: Scope,
+    pub span: Span,
+}
+
+/// All symbols visible inside of one target
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<(SymbolKind, Ident), Symbol>,
+
+    /// The values of all constants that could be evaluated
+    constants: HashMap<Ident, Value>,
+}
+
+impl SymbolTable {
+    /// Build the symbol table of the stage, all of its declarations are global
+    pub fn stage(headers: &Headers) -> SymbolTable {
+        let mut table = SymbolTable::default();
+        table.declare_all(headers, Scope::Global, SymbolKind::Backdrop);
+        table
+    }
+
+    /// Build the symbol table of a sprite on top of the global symbols of the stage
+    ///
+    /// Only the kinds of symbols that are [shared](SymbolKind::is_shared) are visible to it.
+    /// Local declarations take precedence over global ones of the same name.
+    pub fn sprite(headers: &Headers, stage: &SymbolTable) -> SymbolTable {
+        let mut table = SymbolTable::default();
+        let shared = stage
+            .iter()
+            .filter(|s| s.scope == Scope::Global && s.kind.is_shared());
+        for symbol in shared {
+            table.insert(symbol.clone());
+        }
+        table.constants = stage.constants.clone();
+        table.declare_all(headers, Scope::Local, SymbolKind::Costume);
+        table
+    }
+
+    /// Declare the sprites of the project, which are visible to all targets
+    ///
+    /// They aren't declared in any file, so their spans are empty.
+    pub fn declare_sprites<'a>(&mut self, names: impl IntoIterator<Item = &'a Ident>) {
+        for name in names {
+            self.insert(Symbol {
+                name: name.clone(),
+                kind: SymbolKind::Sprite,
+                scope: Scope::Global,
+                span: Span::marker(0),
+            });
+        }
+    }
+
+    /// Declare the broadcasts a library uses, which are checked against the stage later on
+    pub(super) fn declare_broadcasts(&mut self, usage: &BroadcastUsage) {
+        for (name, span) in usage.sent.iter().chain(&usage.received) {
+            self.insert(Symbol {
+                name: name.clone(),
+                kind: SymbolKind::Broadcast,
+                scope: Scope::Global,
+                span: *span,
+            });
+        }
+    }
+
+    pub fn get(&self, kind: SymbolKind, name: &Ident) -> Option<&Symbol> {
+        self.symbols.get(&(kind, name.clone()))
+    }
+
+    /// The value of a constant, if it could be evaluated
+    pub fn constant(&self, name: &Ident) -> Option<&Value> {
+        self.constants.get(name)
+    }
+
+    /// All symbols of any kind with the given name
+    pub fn lookup<'a>(&'a self, name: &'a Ident) -> impl Iterator<Item = &'a Symbol> {
+        self.symbols.values().filter(move |s| &s.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.values()
+    }
+
+    pub(super) fn define_constant(&mut self, name: Ident, value: Value) {
+        self.constants.insert(name, value);
+    }
+
+    fn insert(&mut self, symbol: Symbol) {
+        self.symbols
+            .insert((symbol.kind, symbol.name.clone()), symbol);
+    }
+
+    fn declare_all(&mut self, headers: &Headers, scope: Scope, costume_kind: SymbolKind) {
+        let vars = headers.vars.iter().map(|(id, decl)| (id, decl.span));
+        let consts = headers.consts.iter().map(|(id, (_, span))| (id, *span));
+        let lists = headers.lists.iter().map(|(id, (_, span))| (id, *span));
+        let broadcasts = headers.broadcasts.iter().map(|(id, span)| (id, *span));
+        let costumes = headers
+            .costumes
+            .iter()
+            .map(|(id, (_, _, span))| (id, *span));
+        let sounds = headers.sounds.iter().map(|(id, (_, _, span))| (id, *span));
+
+        let decls = vars
+            .map(|d| (SymbolKind::Variable, d))
+            .chain(consts.map(|d| (SymbolKind::Constant, d)))
+            .chain(lists.map(|d| (SymbolKind::List, d)))
+            .chain(broadcasts.map(|d| (SymbolKind::Broadcast, d)))
+            .chain(costumes.map(|d| (costume_kind, d)))
+            .chain(sounds.map(|d| (SymbolKind::Sound, d)));
+
+        for (kind, (name, span)) in decls {
+            self.insert(Symbol {
+                name: name.clone(),
+                kind,
+                scope,
+                span,
+            });
+        }
+    }
+}
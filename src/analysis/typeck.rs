@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use super::{AnalysisError, AnalysisWarning, Diagnostics, SymbolKind, SymbolTable};
+use crate::catalog::{self, ArgSpec, BlockSpec, Shape, Type};
+use crate::parsing::{
+    Arg, BinOp, BlockCall, Expr, ExprKind, FieldKind, Ident, ParamKind, Script, ScriptKind,
+    Signature, Span, Stmt, StmtKind,
+};
+use scratch_sb3::Value;
+
+pub(super) fn check(
+    scripts: &[Script],
+    symbols: &SymbolTable,
+    procedures: &HashMap<String, &Signature>,
+    diagnostics: &mut Diagnostics,
+) {
+    for script in scripts {
+        let mut checker = TypeChecker {
+            symbols,
+            procedures,
+            params: HashMap::new(),
+            in_clone: None,
+            diagnostics,
+        };
+
+        match &script.kind {
+            ScriptKind::When(hat) => {
+                if let Some(spec) = checker.builtin(hat) {
+                    checker.expect_shape(hat, spec, "hat block", |shape| shape == Shape::Hat);
+                    checker.args(hat, spec);
+                    checker.in_clone = Some(spec.opcode == "control_start_as_clone");
+                }
+            }
+            ScriptKind::Def(signature) => {
+                for param in signature.params() {
+                    checker.params.insert(&param.name, param.kind);
+                }
+            }
+        }
+
+        checker.body(&script.body);
+    }
+}
+
+struct TypeChecker<'a, 'd> {
+    symbols: &'a SymbolTable,
+    procedures: &'a HashMap<String, &'a Signature>,
+    params: HashMap<&'a Ident, ParamKind>,
+
+    /// Whether the script only runs in clones, unknown for custom blocks
+    in_clone: Option<bool>,
+    diagnostics: &'d mut Diagnostics,
+}
+
+impl TypeChecker<'_, '_> {
+    fn body(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            self.stmt(stmt);
+        }
+
+        // Scratch can't attach anything below these blocks
+        if let Some(end) = body.iter().position(ends_script)
+            && let Some(next) = body.get(end + 1)
+        {
+            self.diagnostics.errors.push(AnalysisError::Unreachable {
+                span: next.span,
+                end: body[end].span,
+            });
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Call(call) if call.namespace.is_none() => {
+                // unknown custom blocks are reported by the resolver
+                let Some(signature) = self.procedures.get(&call.pattern()) else {
+                    return;
+                };
+
+                for (arg, param) in call.args().zip(signature.params()) {
+                    match (arg, param.kind) {
+                        (Arg::Reporter(expr), ParamKind::Reporter) => self.expect(expr, Type::Any),
+                        (Arg::Boolean(expr), ParamKind::Boolean) => {
+                            self.expect(expr, Type::Boolean)
+                        }
+                        _ => unreachable!("pattern of the call matches the signature"),
+                    }
+                }
+            }
+            StmtKind::Call(call) => {
+                if let Some(spec) = self.builtin(call) {
+                    self.expect_shape(call, spec, "statement", |shape| {
+                        matches!(shape, Shape::Stack | Shape::Cap)
+                    });
+                    self.args(call, spec);
+
+                    if spec.opcode == "control_delete_this_clone" && self.in_clone == Some(false) {
+                        self.diagnostics
+                            .warnings
+                            .push(AnalysisWarning::DeleteOutsideClone { span: stmt.span });
+                    }
+                }
+            }
+            StmtKind::If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                self.expect(cond, Type::Boolean);
+                self.body(then);
+                if let Some(otherwise) = otherwise {
+                    self.body(otherwise);
+                }
+            }
+            StmtKind::Repeat { times, body } => {
+                self.expect(times, Type::Number);
+                self.body(body);
+            }
+            StmtKind::RepeatUntil { cond, body } | StmtKind::While { cond, body } => {
+                self.expect(cond, Type::Boolean);
+                self.body(body);
+            }
+            StmtKind::Forever(body) => self.body(body),
+            StmtKind::Match {
+                value,
+                arms,
+                default,
+            } => {
+                self.expect(value, Type::Any);
+                for arm in arms {
+                    for pattern in &arm.patterns {
+                        self.expect(pattern, Type::Any);
+                    }
+                    self.body(&arm.body);
+                }
+                if let Some(default) = default {
+                    self.body(default);
+                }
+            }
+            StmtKind::For { from, to, body, .. } => {
+                self.expect(from, Type::Number);
+                self.expect(to, Type::Number);
+                if let (
+                    ExprKind::Literal(Value::Number(start)),
+                    ExprKind::Literal(Value::Number(end)),
+                ) = (&from.kind, &to.kind)
+                    && f32::from(*end) < f32::from(*start)
+                {
+                    self.diagnostics.warnings.push(AnalysisWarning::EmptyRange {
+                        span: Span::new(from.span.start, to.span.end),
+                    });
+                }
+                self.body(body);
+            }
+        }
+    }
+
+    fn args(&mut self, call: &BlockCall, spec: &BlockSpec) {
+        for (arg, arg_spec) in call.args().zip(spec.args) {
+            match (arg, arg_spec) {
+                (Arg::Reporter(expr), ArgSpec::Input { ty, .. }) => {
+                    self.expect(expr, ty.expected())
+                }
+                (Arg::Boolean(expr), ArgSpec::Boolean { .. }) => self.expect(expr, Type::Boolean),
+                // fields are checked by the resolver
+                (
+                    Arg::Field(_),
+                    ArgSpec::Menu { .. } | ArgSpec::Field(_) | ArgSpec::Primitive(_),
+                ) => {}
+                _ => unreachable!("pattern of the call matches the block spec"),
+            }
+        }
+    }
+
+    /// Look up a builtin block, reporting it if it doesn't exist
+    fn builtin(&mut self, call: &BlockCall) -> Option<&'static BlockSpec> {
+        let namespace = call.namespace.as_ref()?;
+        let pattern = call.pattern();
+
+        let spec = catalog::lookup(namespace.as_str(), &pattern);
+        if spec.is_none() {
+            let help = match catalog::similar(namespace.as_str(), &pattern).as_slice() {
+                [] => None,
+                [similar, ..] => Some(format!("did you mean '{namespace}::{}'?", similar.pattern)),
+            };
+
+            self.diagnostics.errors.push(AnalysisError::UnknownBlock {
+                name: format!("{namespace}::{pattern}"),
+                help,
+                span: call.span,
+            });
+        }
+
+        spec
+    }
+
+    fn expect_shape(
+        &mut self,
+        call: &BlockCall,
+        spec: &BlockSpec,
+        expected: &'static str,
+        valid: impl Fn(Shape) -> bool,
+    ) {
+        if !valid(spec.shape) {
+            self.diagnostics.errors.push(AnalysisError::WrongShape {
+                name: format!("{}::{}", call.namespace.as_ref().unwrap(), spec.pattern),
+                shape: spec.shape,
+                expected,
+                span: call.span,
+            });
+        }
+    }
+
+    fn expect(&mut self, expr: &Expr, expected: Type) {
+        let found = self.infer(expr);
+
+        let fits = match (expected, found) {
+            (Type::Boolean, found) => found == Type::Boolean,
+            // Scratch happily displays booleans as text
+            (Type::String, _) => true,
+            (_, Type::Boolean) => false,
+            (Type::Number, Type::String) => {
+                let value = match &expr.kind {
+                    ExprKind::Literal(value) => Some(value),
+                    ExprKind::Ident(name) if !self.params.contains_key(name) => {
+                        self.symbols.constant(name)
+                    }
+                    _ => None,
+                };
+
+                match value {
+                    Some(Value::String(string)) => string.trim().parse::<f64>().is_ok(),
+                    _ => true,
+                }
+            }
+            _ => true,
+        };
+
+        if !fits {
+            self.diagnostics.errors.push(AnalysisError::TypeMismatch {
+                expected,
+                found,
+                span: expr.span,
+            });
+        }
+    }
+
+    fn infer(&mut self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Literal(Value::Number(_)) => Type::Number,
+            ExprKind::Literal(Value::String(_)) => Type::String,
+            ExprKind::Ident(name) => match self.params.get(name) {
+                Some(ParamKind::Boolean) => Type::Boolean,
+                Some(ParamKind::Reporter) => Type::Any,
+                None => match self.symbols.constant(name) {
+                    Some(Value::Number(_)) => Type::Number,
+                    Some(Value::String(_)) => Type::String,
+                    None if self.symbols.get(SymbolKind::Variable, name).is_some() => Type::Any,
+                    None if self.symbols.get(SymbolKind::List, name).is_some() => Type::String,
+                    // undeclared identifiers are reported by the resolver
+                    None => Type::Any,
+                },
+            },
+            ExprKind::Call(call) => {
+                let Some(spec) = self.builtin(call) else {
+                    return Type::Any;
+                };
+
+                self.expect_shape(call, spec, "reporter", |shape| {
+                    matches!(shape, Shape::Reporter(_))
+                });
+                self.args(call, spec);
+
+                match spec.shape {
+                    Shape::Reporter(ty) => ty,
+                    _ => Type::Any,
+                }
+            }
+            ExprKind::Binary(op, lhs, rhs) => match op {
+                BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div => {
+                    self.expect(lhs, Type::Number);
+                    self.expect(rhs, Type::Number);
+                    Type::Number
+                }
+                BinOp::Greater | BinOp::Less | BinOp::Equals => {
+                    self.expect(lhs, Type::Any);
+                    self.expect(rhs, Type::Any);
+                    Type::Boolean
+                }
+            },
+        }
+    }
+}
+
+/// Whether nothing after the statement ever runs
+fn ends_script(stmt: &Stmt) -> bool {
+    let StmtKind::Call(call) = &stmt.kind else {
+        return matches!(stmt.kind, StmtKind::Forever(_));
+    };
+
+    let Some(spec) = call
+        .namespace
+        .as_ref()
+        .and_then(|namespace| catalog::lookup(namespace.as_str(), &call.pattern()))
+    else {
+        return false;
+    };
+
+    match (spec.opcode, call.args().next(), spec.args.first()) {
+        ("control_stop", Some(Arg::Field(field)), Some(ArgSpec::Field(field_spec))) => {
+            let option = match &field.kind {
+                FieldKind::Ident(name) => field_spec.option_for_ident(name.as_str()),
+                FieldKind::String(string) => field_spec.option_for_str(string),
+            };
+            option.is_some_and(catalog::stops_script)
+        }
+        _ => spec.shape == Shape::Cap,
+    }
+}
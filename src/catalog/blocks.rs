@@ -0,0 +1,818 @@
+use super::{ArgSpec, BlockSpec, FieldSpec, InputType, Shape, Type};
+use crate::analysis::SymbolKind;
+
+const fn hat(pattern: &'static str, opcode: &'static str, args: &'static [ArgSpec]) -> BlockSpec {
+    BlockSpec {
+        pattern,
+        opcode,
+        shape: Shape::Hat,
+        args,
+    }
+}
+
+const fn stack(pattern: &'static str, opcode: &'static str, args: &'static [ArgSpec]) -> BlockSpec {
+    BlockSpec {
+        pattern,
+        opcode,
+        shape: Shape::Stack,
+        args,
+    }
+}
+
+const fn cap(pattern: &'static str, opcode: &'static str, args: &'static [ArgSpec]) -> BlockSpec {
+    BlockSpec {
+        pattern,
+        opcode,
+        shape: Shape::Cap,
+        args,
+    }
+}
+
+const fn reporter(
+    pattern: &'static str,
+    opcode: &'static str,
+    ty: Type,
+    args: &'static [ArgSpec],
+) -> BlockSpec {
+    BlockSpec {
+        pattern,
+        opcode,
+        shape: Shape::Reporter(ty),
+        args,
+    }
+}
+
+const fn input(name: &'static str, ty: InputType) -> ArgSpec {
+    ArgSpec::Input { name, ty }
+}
+
+const fn number(name: &'static str) -> ArgSpec {
+    input(name, InputType::Number)
+}
+
+const fn text(name: &'static str) -> ArgSpec {
+    input(name, InputType::Text)
+}
+
+const fn boolean(name: &'static str) -> ArgSpec {
+    ArgSpec::Boolean { name }
+}
+
+const fn field(
+    name: &'static str,
+    refers: Option<SymbolKind>,
+    options: &'static [&'static str],
+) -> FieldSpec {
+    FieldSpec {
+        name,
+        refers,
+        options,
+    }
+}
+
+const fn menu(
+    opcode: &'static str,
+    name: &'static str,
+    refers: Option<SymbolKind>,
+    options: &'static [&'static str],
+) -> ArgSpec {
+    ArgSpec::Menu {
+        input: name,
+        opcode,
+        field: field(name, refers, options),
+    }
+}
+
+/// A menu whose input isn't named like its field, as in most extensions
+const fn menu_input(
+    input: &'static str,
+    opcode: &'static str,
+    name: &'static str,
+    options: &'static [&'static str],
+) -> ArgSpec {
+    ArgSpec::Menu {
+        input,
+        opcode,
+        field: field(name, None, options),
+    }
+}
+
+const fn broadcast(name: &'static str) -> ArgSpec {
+    ArgSpec::Primitive(field(name, Some(SymbolKind::Broadcast), &[]))
+}
+
+const fn variable(name: &'static str) -> ArgSpec {
+    ArgSpec::Field(field(name, Some(SymbolKind::Variable), &[]))
+}
+
+const fn list(name: &'static str) -> ArgSpec {
+    ArgSpec::Field(field(name, Some(SymbolKind::List), &[]))
+}
+
+const fn options(name: &'static str, options: &'static [&'static str]) -> ArgSpec {
+    ArgSpec::Field(field(name, None, options))
+}
+
+const KEYS: &[&str] = &[
+    "space",
+    "up arrow",
+    "down arrow",
+    "right arrow",
+    "left arrow",
+    "any",
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+    "f",
+    "g",
+    "h",
+    "i",
+    "j",
+    "k",
+    "l",
+    "m",
+    "n",
+    "o",
+    "p",
+    "q",
+    "r",
+    "s",
+    "t",
+    "u",
+    "v",
+    "w",
+    "x",
+    "y",
+    "z",
+    "0",
+    "1",
+    "2",
+    "3",
+    "4",
+    "5",
+    "6",
+    "7",
+    "8",
+    "9",
+];
+
+const LOOKS_EFFECTS: &[&str] = &[
+    "COLOR",
+    "FISHEYE",
+    "WHIRL",
+    "PIXELATE",
+    "MOSAIC",
+    "BRIGHTNESS",
+    "GHOST",
+];
+
+pub const MOTION: &[BlockSpec] = &[
+    stack("move_()_steps", "motion_movesteps", &[number("STEPS")]),
+    stack(
+        "turn_right_()_degrees",
+        "motion_turnright",
+        &[number("DEGREES")],
+    ),
+    stack(
+        "turn_left_()_degrees",
+        "motion_turnleft",
+        &[number("DEGREES")],
+    ),
+    stack(
+        "go_to_[]",
+        "motion_goto",
+        &[menu(
+            "motion_goto_menu",
+            "TO",
+            None,
+            &["_random_", "_mouse_"],
+        )],
+    ),
+    stack(
+        "go_to_x_()_y_()",
+        "motion_gotoxy",
+        &[number("X"), number("Y")],
+    ),
+    stack(
+        "glide_()_secs_to_[]",
+        "motion_glideto",
+        &[
+            number("SECS"),
+            menu("motion_glideto_menu", "TO", None, &["_random_", "_mouse_"]),
+        ],
+    ),
+    stack(
+        "glide_()_secs_to_x_()_y_()",
+        "motion_glidesecstoxy",
+        &[number("SECS"), number("X"), number("Y")],
+    ),
+    stack(
+        "point_in_direction_()",
+        "motion_pointindirection",
+        &[input("DIRECTION", InputType::Angle)],
+    ),
+    stack(
+        "point_towards_[]",
+        "motion_pointtowards",
+        &[menu(
+            "motion_pointtowards_menu",
+            "TOWARDS",
+            None,
+            &["_mouse_", "_random_"],
+        )],
+    ),
+    stack("change_x_by_()", "motion_changexby", &[number("DX")]),
+    stack("set_x_to_()", "motion_setx", &[number("X")]),
+    stack("change_y_by_()", "motion_changeyby", &[number("DY")]),
+    stack("set_y_to_()", "motion_sety", &[number("Y")]),
+    stack("if_on_edge_bounce", "motion_ifonedgebounce", &[]),
+    stack(
+        "set_rotation_style_[]",
+        "motion_setrotationstyle",
+        &[options(
+            "STYLE",
+            &["left-right", "don't rotate", "all around"],
+        )],
+    ),
+    reporter("x_position", "motion_xposition", Type::Number, &[]),
+    reporter("y_position", "motion_yposition", Type::Number, &[]),
+    reporter("direction", "motion_direction", Type::Number, &[]),
+];
+
+pub const LOOKS: &[BlockSpec] = &[
+    stack(
+        "say_()_for_()_seconds",
+        "looks_sayforsecs",
+        &[text("MESSAGE"), number("SECS")],
+    ),
+    stack("say_()", "looks_say", &[text("MESSAGE")]),
+    stack(
+        "think_()_for_()_seconds",
+        "looks_thinkforsecs",
+        &[text("MESSAGE"), number("SECS")],
+    ),
+    stack("think_()", "looks_think", &[text("MESSAGE")]),
+    stack(
+        "switch_costume_to_[]",
+        "looks_switchcostumeto",
+        &[menu(
+            "looks_costume",
+            "COSTUME",
+            Some(SymbolKind::Costume),
+            &[],
+        )],
+    ),
+    stack("next_costume", "looks_nextcostume", &[]),
+    stack(
+        "switch_backdrop_to_[]",
+        "looks_switchbackdropto",
+        &[menu(
+            "looks_backdrops",
+            "BACKDROP",
+            Some(SymbolKind::Backdrop),
+            &["next backdrop", "previous backdrop", "random backdrop"],
+        )],
+    ),
+    stack(
+        "switch_backdrop_to_[]_and_wait",
+        "looks_switchbackdroptoandwait",
+        &[menu(
+            "looks_backdrops",
+            "BACKDROP",
+            Some(SymbolKind::Backdrop),
+            &["next backdrop", "previous backdrop", "random backdrop"],
+        )],
+    ),
+    stack("next_backdrop", "looks_nextbackdrop", &[]),
+    stack(
+        "change_size_by_()",
+        "looks_changesizeby",
+        &[number("CHANGE")],
+    ),
+    stack(
+        "set_size_to_()_percent",
+        "looks_setsizeto",
+        &[number("SIZE")],
+    ),
+    stack(
+        "change_[]_effect_by_()",
+        "looks_changeeffectby",
+        &[options("EFFECT", LOOKS_EFFECTS), number("CHANGE")],
+    ),
+    stack(
+        "set_[]_effect_to_()",
+        "looks_seteffectto",
+        &[options("EFFECT", LOOKS_EFFECTS), number("VALUE")],
+    ),
+    stack("clear_graphic_effects", "looks_cleargraphiceffects", &[]),
+    stack("show", "looks_show", &[]),
+    stack("hide", "looks_hide", &[]),
+    stack(
+        "go_to_[]_layer",
+        "looks_gotofrontback",
+        &[options("FRONT_BACK", &["front", "back"])],
+    ),
+    stack(
+        "go_[]_()_layers",
+        "looks_goforwardbackward",
+        &[
+            options("FORWARD_BACKWARD", &["forward", "backward"]),
+            input("NUM", InputType::Integer),
+        ],
+    ),
+    reporter(
+        "costume_[]",
+        "looks_costumenumbername",
+        Type::Any,
+        &[options("NUMBER_NAME", &["number", "name"])],
+    ),
+    reporter(
+        "backdrop_[]",
+        "looks_backdropnumbername",
+        Type::Any,
+        &[options("NUMBER_NAME", &["number", "name"])],
+    ),
+    reporter("size", "looks_size", Type::Number, &[]),
+];
+
+pub const SOUND: &[BlockSpec] = &[
+    stack(
+        "play_sound_[]_until_done",
+        "sound_playuntildone",
+        &[menu(
+            "sound_sounds_menu",
+            "SOUND_MENU",
+            Some(SymbolKind::Sound),
+            &[],
+        )],
+    ),
+    stack(
+        "start_sound_[]",
+        "sound_play",
+        &[menu(
+            "sound_sounds_menu",
+            "SOUND_MENU",
+            Some(SymbolKind::Sound),
+            &[],
+        )],
+    ),
+    stack("stop_all_sounds", "sound_stopallsounds", &[]),
+    stack(
+        "change_[]_effect_by_()",
+        "sound_changeeffectby",
+        &[options("EFFECT", &["PITCH", "PAN"]), number("VALUE")],
+    ),
+    stack(
+        "set_[]_effect_to_()",
+        "sound_seteffectto",
+        &[options("EFFECT", &["PITCH", "PAN"]), number("VALUE")],
+    ),
+    stack("clear_sound_effects", "sound_cleareffects", &[]),
+    stack(
+        "change_volume_by_()",
+        "sound_changevolumeby",
+        &[number("VOLUME")],
+    ),
+    stack(
+        "set_volume_to_()_percent",
+        "sound_setvolumeto",
+        &[number("VOLUME")],
+    ),
+    reporter("volume", "sound_volume", Type::Number, &[]),
+];
+
+pub const EVENTS: &[BlockSpec] = &[
+    hat("flag_clicked", "event_whenflagclicked", &[]),
+    hat("flag", "event_whenflagclicked", &[]),
+    hat(
+        "key_[]_pressed",
+        "event_whenkeypressed",
+        &[options("KEY_OPTION", KEYS)],
+    ),
+    hat("this_sprite_clicked", "event_whenthisspriteclicked", &[]),
+    hat("stage_clicked", "event_whenstageclicked", &[]),
+    hat(
+        "backdrop_switches_to_[]",
+        "event_whenbackdropswitchesto",
+        &[ArgSpec::Field(field(
+            "BACKDROP",
+            Some(SymbolKind::Backdrop),
+            &[],
+        ))],
+    ),
+    hat(
+        "i_receive_[]",
+        "event_whenbroadcastreceived",
+        &[ArgSpec::Field(field(
+            "BROADCAST_OPTION",
+            Some(SymbolKind::Broadcast),
+            &[],
+        ))],
+    ),
+    hat(
+        "[]_exceeds_()",
+        "event_whengreaterthan",
+        &[
+            options("WHENGREATERTHANMENU", &["LOUDNESS", "TIMER"]),
+            number("VALUE"),
+        ],
+    ),
+    stack(
+        "broadcast_[]",
+        "event_broadcast",
+        &[broadcast("BROADCAST_INPUT")],
+    ),
+    stack(
+        "broadcast_[]_and_wait",
+        "event_broadcastandwait",
+        &[broadcast("BROADCAST_INPUT")],
+    ),
+];
+
+pub const CONTROL: &[BlockSpec] = &[
+    hat("start_as_clone", "control_start_as_clone", &[]),
+    hat("cloned", "control_start_as_clone", &[]),
+    stack(
+        "create_clone_of_[]",
+        "control_create_clone_of",
+        &[menu(
+            "control_create_clone_of_menu",
+            "CLONE_OPTION",
+            Some(SymbolKind::Sprite),
+            &["_myself_"],
+        )],
+    ),
+    cap("delete_this_clone", "control_delete_this_clone", &[]),
+    stack(
+        "stop_[]",
+        "control_stop",
+        &[options(
+            "STOP_OPTION",
+            &[
+                "all",
+                "this script",
+                "other scripts in sprite",
+                "other scripts in stage",
+            ],
+        )],
+    ),
+    stack(
+        "wait_()_secs",
+        "control_wait",
+        &[input("DURATION", InputType::PositiveNumber)],
+    ),
+    stack(
+        "wait_until_<>",
+        "control_wait_until",
+        &[boolean("CONDITION")],
+    ),
+];
+
+pub const SENSING: &[BlockSpec] = &[
+    reporter(
+        "touching_[]",
+        "sensing_touchingobject",
+        Type::Boolean,
+        &[menu(
+            "sensing_touchingobjectmenu",
+            "TOUCHINGOBJECTMENU",
+            None,
+            &["_mouse_", "_edge_"],
+        )],
+    ),
+    reporter(
+        "touching_color_()",
+        "sensing_touchingcolor",
+        Type::Boolean,
+        &[input("COLOR", InputType::Color)],
+    ),
+    reporter(
+        "color_()_is_touching_()",
+        "sensing_coloristouchingcolor",
+        Type::Boolean,
+        &[
+            input("COLOR", InputType::Color),
+            input("COLOR2", InputType::Color),
+        ],
+    ),
+    reporter(
+        "distance_to_[]",
+        "sensing_distanceto",
+        Type::Number,
+        &[menu(
+            "sensing_distancetomenu",
+            "DISTANCETOMENU",
+            None,
+            &["_mouse_"],
+        )],
+    ),
+    stack("ask_()_and_wait", "sensing_askandwait", &[text("QUESTION")]),
+    reporter("answer", "sensing_answer", Type::String, &[]),
+    reporter(
+        "key_[]_pressed",
+        "sensing_keypressed",
+        Type::Boolean,
+        &[menu("sensing_keyoptions", "KEY_OPTION", None, KEYS)],
+    ),
+    reporter("mouse_down", "sensing_mousedown", Type::Boolean, &[]),
+    reporter("mouse_x", "sensing_mousex", Type::Number, &[]),
+    reporter("mouse_y", "sensing_mousey", Type::Number, &[]),
+    stack(
+        "set_drag_mode_[]",
+        "sensing_setdragmode",
+        &[options("DRAG_MODE", &["draggable", "not draggable"])],
+    ),
+    reporter("loudness", "sensing_loudness", Type::Number, &[]),
+    reporter("timer", "sensing_timer", Type::Number, &[]),
+    stack("reset_timer", "sensing_resettimer", &[]),
+    reporter(
+        "[]_of_[]",
+        "sensing_of",
+        Type::Any,
+        &[
+            options(
+                "PROPERTY",
+                &[
+                    "x position",
+                    "y position",
+                    "direction",
+                    "costume #",
+                    "costume name",
+                    "size",
+                    "volume",
+                    "backdrop #",
+                    "backdrop name",
+                ],
+            ),
+            menu("sensing_of_object_menu", "OBJECT", None, &["_stage_"]),
+        ],
+    ),
+    reporter(
+        "current_[]",
+        "sensing_current",
+        Type::Number,
+        &[options(
+            "CURRENTMENU",
+            &[
+                "YEAR",
+                "MONTH",
+                "DATE",
+                "DAYOFWEEK",
+                "HOUR",
+                "MINUTE",
+                "SECOND",
+            ],
+        )],
+    ),
+    reporter(
+        "days_since_2000",
+        "sensing_dayssince2000",
+        Type::Number,
+        &[],
+    ),
+    reporter("username", "sensing_username", Type::String, &[]),
+];
+
+pub const OPERATOR: &[BlockSpec] = &[
+    reporter(
+        "pick_random_()_to_()",
+        "operator_random",
+        Type::Number,
+        &[number("FROM"), number("TO")],
+    ),
+    reporter(
+        "<>_and_<>",
+        "operator_and",
+        Type::Boolean,
+        &[boolean("OPERAND1"), boolean("OPERAND2")],
+    ),
+    reporter(
+        "<>_or_<>",
+        "operator_or",
+        Type::Boolean,
+        &[boolean("OPERAND1"), boolean("OPERAND2")],
+    ),
+    reporter(
+        "not_<>",
+        "operator_not",
+        Type::Boolean,
+        &[boolean("OPERAND")],
+    ),
+    reporter(
+        "join_()_()",
+        "operator_join",
+        Type::String,
+        &[text("STRING1"), text("STRING2")],
+    ),
+    reporter(
+        "letter_()_of_()",
+        "operator_letter_of",
+        Type::String,
+        &[input("LETTER", InputType::PositiveInteger), text("STRING")],
+    ),
+    reporter(
+        "length_of_()",
+        "operator_length",
+        Type::Number,
+        &[text("STRING")],
+    ),
+    reporter(
+        "()_contains_()",
+        "operator_contains",
+        Type::Boolean,
+        &[text("STRING1"), text("STRING2")],
+    ),
+    reporter(
+        "()_mod_()",
+        "operator_mod",
+        Type::Number,
+        &[number("NUM1"), number("NUM2")],
+    ),
+    reporter("round_()", "operator_round", Type::Number, &[number("NUM")]),
+    reporter(
+        "[]_of_()",
+        "operator_mathop",
+        Type::Number,
+        &[
+            options(
+                "OPERATOR",
+                &[
+                    "abs", "floor", "ceiling", "sqrt", "sin", "cos", "tan", "asin", "acos", "atan",
+                    "ln", "log", "e ^", "10 ^",
+                ],
+            ),
+            number("NUM"),
+        ],
+    ),
+];
+
+/// Blocks working on variables and lists, mostly written with dedicated syntax like `items.push(x)`
+pub const DATA: &[BlockSpec] = &[
+    stack(
+        "set_[]_to_()",
+        "data_setvariableto",
+        &[variable("VARIABLE"), text("VALUE")],
+    ),
+    stack(
+        "change_[]_by_()",
+        "data_changevariableby",
+        &[variable("VARIABLE"), number("VALUE")],
+    ),
+    stack(
+        "show_variable_[]",
+        "data_showvariable",
+        &[variable("VARIABLE")],
+    ),
+    stack(
+        "hide_variable_[]",
+        "data_hidevariable",
+        &[variable("VARIABLE")],
+    ),
+    stack(
+        "add_()_to_[]",
+        "data_addtolist",
+        &[text("ITEM"), list("LIST")],
+    ),
+    stack(
+        "delete_()_of_[]",
+        "data_deleteoflist",
+        &[input("INDEX", InputType::Integer), list("LIST")],
+    ),
+    stack("delete_all_of_[]", "data_deletealloflist", &[list("LIST")]),
+    stack(
+        "insert_()_at_()_of_[]",
+        "data_insertatlist",
+        &[
+            text("ITEM"),
+            input("INDEX", InputType::Integer),
+            list("LIST"),
+        ],
+    ),
+    reporter(
+        "item_()_of_[]",
+        "data_itemoflist",
+        Type::Any,
+        &[input("INDEX", InputType::Integer), list("LIST")],
+    ),
+    reporter(
+        "length_of_[]",
+        "data_lengthoflist",
+        Type::Number,
+        &[list("LIST")],
+    ),
+    reporter(
+        "[]_contains_()",
+        "data_listcontainsitem",
+        Type::Boolean,
+        &[list("LIST"), text("ITEM")],
+    ),
+];
+
+pub const VIDEO_SENSING: &[BlockSpec] = &[hat(
+    "motion_exceeds_()",
+    "videoSensing_whenMotionGreaterThan",
+    &[number("REFERENCE")],
+)];
+
+pub const MAKEYMAKEY: &[BlockSpec] = &[
+    hat(
+        "key_[]_pressed",
+        "makeymakey_whenMakeyKeyPressed",
+        &[menu(
+            "makeymakey_menu_KEY",
+            "KEY",
+            None,
+            &[
+                "SPACE", "LEFT", "RIGHT", "UP", "DOWN", "w", "a", "s", "d", "f", "g",
+            ],
+        )],
+    ),
+    hat(
+        "[]_pressed_in_order",
+        "makeymakey_whenCodePressed",
+        &[menu(
+            "makeymakey_menu_SEQUENCE",
+            "SEQUENCE",
+            None,
+            &[
+                "LEFT UP RIGHT",
+                "RIGHT UP LEFT",
+                "LEFT RIGHT",
+                "RIGHT LEFT",
+                "UP DOWN",
+                "DOWN UP",
+                "UP RIGHT DOWN LEFT",
+                "UP LEFT DOWN RIGHT",
+                "UP UP DOWN DOWN LEFT RIGHT LEFT RIGHT",
+            ],
+        )],
+    ),
+];
+
+pub const MICROBIT: &[BlockSpec] = &[
+    hat(
+        "button_[]_pressed",
+        "microbit_whenButtonPressed",
+        &[menu_input(
+            "BTN",
+            "microbit_menu_buttons",
+            "buttons",
+            &["A", "B", "any"],
+        )],
+    ),
+    hat(
+        "gesture_[]",
+        "microbit_whenGesture",
+        &[menu_input(
+            "GESTURE",
+            "microbit_menu_gestures",
+            "gestures",
+            &["moved", "shaken", "jumped"],
+        )],
+    ),
+    hat(
+        "tilted_[]",
+        "microbit_whenTilted",
+        &[menu_input(
+            "DIRECTION",
+            "microbit_menu_tiltDirectionAny",
+            "tiltDirectionAny",
+            &["front", "back", "left", "right", "any"],
+        )],
+    ),
+    hat(
+        "pin_[]_connected",
+        "microbit_whenPinConnected",
+        &[menu_input(
+            "PIN",
+            "microbit_menu_touchPins",
+            "touchPins",
+            &["0", "1", "2"],
+        )],
+    ),
+];
+
+pub const EV3: &[BlockSpec] = &[
+    hat(
+        "button_[]_pressed",
+        "ev3_whenButtonPressed",
+        &[menu_input(
+            "PORT",
+            "ev3_menu_sensorPorts",
+            "sensorPorts",
+            &["1", "2", "3", "4"],
+        )],
+    ),
+    hat(
+        "distance_below_()",
+        "ev3_whenDistanceLessThan",
+        &[number("DISTANCE")],
+    ),
+    hat(
+        "brightness_below_()",
+        "ev3_whenBrightnessLessThan",
+        &[number("DISTANCE")],
+    ),
+];
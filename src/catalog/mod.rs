@@ -0,0 +1,186 @@
+//! Known Scratch blocks and how they are written in `.scr` files
+
+mod blocks;
+
+use std::fmt;
+
+use crate::analysis::SymbolKind;
+
+/// The type of value an expression evaluates to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Number,
+    String,
+    Boolean,
+    /// Either a number or a string, only known at runtime (e.g. variables)
+    Any,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Number => write!(f, "number"),
+            Type::String => write!(f, "string"),
+            Type::Boolean => write!(f, "boolean"),
+            Type::Any => write!(f, "value"),
+        }
+    }
+}
+
+/// The shape of a block, which decides where it can be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Hat,
+    Stack,
+    /// A stack block that nothing can be attached to
+    Cap,
+    Reporter(Type),
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shape::Hat => write!(f, "hat block"),
+            Shape::Stack => write!(f, "stack block"),
+            Shape::Cap => write!(f, "cap block"),
+            Shape::Reporter(Type::Boolean) => write!(f, "boolean reporter"),
+            Shape::Reporter(_) => write!(f, "reporter"),
+        }
+    }
+}
+
+/// The kind of primitive that fills a round input slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputType {
+    Number,
+    PositiveNumber,
+    PositiveInteger,
+    Integer,
+    Angle,
+    Color,
+    Text,
+}
+
+impl InputType {
+    /// The type of value the slot expects
+    pub fn expected(&self) -> Type {
+        match self {
+            InputType::Color | InputType::Text => Type::String,
+            _ => Type::Number,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FieldSpec {
+    pub name: &'static str,
+
+    /// The kind of symbol the field refers to, if any
+    pub refers: Option<SymbolKind>,
+
+    /// Fixed values the field accepts besides the referred symbols
+    pub options: &'static [&'static str],
+}
+
+impl FieldSpec {
+    /// Find the fixed option an identifier like `edge` or `up_arrow` stands for
+    pub fn option_for_ident(&self, ident: &str) -> Option<&'static str> {
+        self.options.iter().copied().find(|option| {
+            option.eq_ignore_ascii_case(ident)
+                || option.trim_matches('_').eq_ignore_ascii_case(ident)
+                || option.replace(' ', "_").eq_ignore_ascii_case(ident)
+        })
+    }
+
+    /// Find the fixed option a string literal stands for
+    pub fn option_for_str(&self, string: &str) -> Option<&'static str> {
+        self.options
+            .iter()
+            .copied()
+            .find(|option| *option == string)
+    }
+}
+
+#[derive(Debug)]
+pub enum ArgSpec {
+    /// A round slot `(...)` filled with a primitive or a reporter
+    Input { name: &'static str, ty: InputType },
+
+    /// A hexagonal slot `<...>`
+    Boolean { name: &'static str },
+
+    /// A dropdown `[...]` living in a shadow block of its own
+    Menu {
+        /// Name of the input holding the shadow block, mostly the same as the field's
+        input: &'static str,
+        opcode: &'static str,
+        field: FieldSpec,
+    },
+
+    /// A dropdown `[...]` directly on the block
+    Field(FieldSpec),
+
+    /// A dropdown `[...]` stored as a primitive in the input of the same name, as broadcast menus are
+    Primitive(FieldSpec),
+}
+
+#[derive(Debug)]
+pub struct BlockSpec {
+    /// How the block is written without its arguments, e.g. `play_sound_[]_until_done`
+    pub pattern: &'static str,
+    pub opcode: &'static str,
+    pub shape: Shape,
+    pub args: &'static [ArgSpec],
+}
+
+/// Look up a builtin block by its namespace and [pattern](crate::parsing::BlockCall::pattern)
+pub fn lookup(namespace: &str, pattern: &str) -> Option<&'static BlockSpec> {
+    namespace_blocks(namespace)?
+        .iter()
+        .find(|spec| spec.pattern == pattern)
+}
+
+/// Whether a `control_stop` block with this option ends its script
+///
+/// Scratch then draws it as a cap block, otherwise blocks can be attached below it.
+pub fn stops_script(option: &str) -> bool {
+    matches!(option, "all" | "this script")
+}
+
+/// Builtin blocks of a namespace that only differ from `pattern` in the kind of their slots
+pub fn similar(namespace: &str, pattern: &str) -> Vec<&'static BlockSpec> {
+    let Some(blocks) = namespace_blocks(namespace) else {
+        return Vec::new();
+    };
+
+    let wanted = skeleton(pattern);
+    blocks
+        .iter()
+        .filter(|spec| skeleton(spec.pattern) == wanted)
+        .collect()
+}
+
+fn namespace_blocks(namespace: &str) -> Option<&'static [BlockSpec]> {
+    Some(match namespace {
+        "motion" => blocks::MOTION,
+        "looks" => blocks::LOOKS,
+        "sound" => blocks::SOUND,
+        "events" => blocks::EVENTS,
+        "control" => blocks::CONTROL,
+        "sensing" => blocks::SENSING,
+        "operator" => blocks::OPERATOR,
+        "data" => blocks::DATA,
+        "videoSensing" => blocks::VIDEO_SENSING,
+        "makeymakey" => blocks::MAKEYMAKEY,
+        "microbit" => blocks::MICROBIT,
+        "ev3" => blocks::EV3,
+        _ => return None,
+    })
+}
+
+fn skeleton(pattern: &str) -> String {
+    pattern
+        .replace("()", "#")
+        .replace("<>", "#")
+        .replace("[]", "#")
+}
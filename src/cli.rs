@@ -41,6 +41,10 @@ pub struct BuildArgs {
     /// Don't zip the built Scratch project
     #[arg(long, short = 'Z')]
     pub no_zip: bool,
+
+    /// How much to optimize the generated blocks (0: not at all, 1: fold constants, 2: also remove dead code)
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(0..=2))]
+    pub opt_level: u8,
 }
 
 #[derive(Args, Debug)]
@@ -0,0 +1,155 @@
+use scratch_sb3::block::{self, CodePosition, PrimitiveBlock, ValueType};
+use scratch_sb3::{Id, IdGenerator, Number, Value};
+
+use super::ir::{Block, Expr, Input, Script};
+use crate::catalog::InputType;
+
+/// Horizontal distance between two scripts in the editor
+const SCRIPT_SPACING: i32 = 400;
+
+/// Flatten scripts into the blocks of a target, linked by their IDs
+pub(super) fn emit(scripts: Vec<Script>, ids: &mut IdGenerator) -> Vec<(Id, block::Block)> {
+    let mut emitter = Emitter {
+        ids,
+        blocks: Vec::new(),
+    };
+
+    for (x, script) in (0..).step_by(SCRIPT_SPACING as usize).zip(scripts) {
+        let position = CodePosition::new(Number::Integer(x), Number::Integer(0));
+
+        let mut stack = vec![script.top];
+        stack.extend(script.body);
+        emitter.stack(stack, None, Some(position));
+    }
+
+    emitter.blocks
+}
+
+struct Emitter<'a> {
+    ids: &'a mut IdGenerator,
+    blocks: Vec<(Id, block::Block)>,
+}
+
+impl Emitter<'_> {
+    /// Emit blocks stacked onto each other and return the ID of the first one
+    fn stack(
+        &mut self,
+        stack: Vec<Block>,
+        parent: Option<Id>,
+        position: Option<CodePosition>,
+    ) -> Option<Id> {
+        let ids: Vec<Id> = stack.iter().map(|_| self.ids.generate()).collect();
+
+        let mut position = position;
+        for (i, block) in stack.into_iter().enumerate() {
+            let parent = if i == 0 { parent } else { Some(ids[i - 1]) };
+            let next = ids.get(i + 1).copied();
+            self.block(ids[i], block, parent, next, position.take(), false);
+        }
+
+        ids.first().copied()
+    }
+
+    fn block(
+        &mut self,
+        id: Id,
+        block: Block,
+        parent: Option<Id>,
+        next: Option<Id>,
+        position: Option<CodePosition>,
+        shadow: bool,
+    ) {
+        let mut builder = block::Block::builder().full(block.opcode.to_string());
+        if let Some(parent) = parent {
+            builder = builder.parent(parent);
+        }
+        if let Some(next) = next {
+            builder = builder.next(next);
+        }
+        if let Some(position) = position {
+            builder = builder.top_level_pos(position);
+        }
+        if shadow {
+            builder = builder.shadow();
+        }
+
+        for (name, input) in block.inputs {
+            let input = match input {
+                Input::Value(ty, expr) => self.value(id, ty, expr),
+                Input::Boolean(None) => continue,
+                Input::Boolean(Some(Expr::Block(child))) => {
+                    let child_id = self.ids.generate();
+                    self.block(child_id, *child, Some(id), None, None, false);
+                    block::Input::builder().id(child_id)
+                }
+                Input::Boolean(Some(_)) => unreachable!("boolean slots only hold blocks"),
+                Input::Shadow(child) => {
+                    let child_id = self.ids.generate();
+                    self.block(child_id, child, Some(id), None, None, true);
+                    block::Input::builder().shadow().id(child_id)
+                }
+                Input::Substack(body) => match self.stack(body, Some(id), None) {
+                    Some(first) => block::Input::builder().id(first),
+                    None => continue,
+                },
+                Input::Broadcast(name, broadcast) => block::Input::builder()
+                    .shadow()
+                    .primitive(PrimitiveBlock::builder().broadcast(name, broadcast)),
+            };
+            builder = builder.add_input(name, input);
+        }
+
+        for (name, field) in block.fields {
+            builder = builder.add_field(name.to_string(), field.value, field.id);
+        }
+
+        if let Some(mutation) = block.mutation {
+            builder = builder.mutation(mutation);
+        }
+
+        self.blocks
+            .push((id, block::Block::Full(Box::new(builder.build()))));
+    }
+
+    fn value(&mut self, parent: Id, ty: InputType, expr: Expr) -> block::Input {
+        let obscuring = block::Input::builder().obscuring();
+        let input = match expr {
+            Expr::Literal(value) => {
+                return block::Input::builder()
+                    .shadow()
+                    .primitive(primitive(ty, value));
+            }
+            Expr::Variable(name, id) => {
+                obscuring.primitive(PrimitiveBlock::builder().variable(name, id, None))
+            }
+            Expr::List(name, id) => {
+                obscuring.primitive(PrimitiveBlock::builder().list(name, id, None))
+            }
+            Expr::Block(child) => {
+                let child_id = self.ids.generate();
+                self.block(child_id, *child, Some(parent), None, None, false);
+                obscuring.id(child_id)
+            }
+        };
+
+        // the shadow shows up again once the reporter is dragged out of the slot
+        let default = match ty {
+            InputType::Color => Value::String(String::from("#000000")),
+            _ => Value::String(String::new()),
+        };
+        input.shadow_primitve(primitive(ty, default))
+    }
+}
+
+fn primitive(ty: InputType, value: Value) -> PrimitiveBlock {
+    let ty = match ty {
+        InputType::Number => ValueType::Number,
+        InputType::PositiveNumber => ValueType::PositiveNumber,
+        InputType::PositiveInteger => ValueType::PositiveInteger,
+        InputType::Integer => ValueType::Integer,
+        InputType::Angle => ValueType::Angle,
+        InputType::Color => ValueType::Color,
+        InputType::Text => ValueType::Text,
+    };
+    PrimitiveBlock::Simple(ty, value)
+}
@@ -0,0 +1,97 @@
+//! A tree of Scratch blocks, easy to transform before it's flattened into [`FullBlock`](scratch_sb3::block::FullBlock)s
+
+use scratch_sb3::block::Mutation;
+use scratch_sb3::{Id, Name, Value};
+
+use crate::catalog::InputType;
+
+/// A hat block or custom block definition with the blocks stacked below it
+#[derive(Debug)]
+pub struct Script {
+    pub top: Block,
+    pub body: Vec<Block>,
+}
+
+#[derive(Debug)]
+pub struct Block {
+    pub opcode: &'static str,
+    pub inputs: Vec<(Name, Input)>,
+    pub fields: Vec<(&'static str, Field)>,
+    pub mutation: Option<Mutation>,
+}
+
+impl Block {
+    pub fn new(opcode: &'static str) -> Block {
+        Block {
+            opcode,
+            inputs: Vec::new(),
+            fields: Vec::new(),
+            mutation: None,
+        }
+    }
+
+    pub fn input(&self, name: &str) -> Option<&Input> {
+        self.inputs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, input)| input)
+    }
+
+    pub fn input_mut(&mut self, name: &str) -> Option<&mut Input> {
+        self.inputs
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, input)| input)
+    }
+
+    /// Take the blocks out of a substack input, leaving it empty
+    pub fn take_substack(&mut self, name: &str) -> Vec<Block> {
+        match self.input_mut(name) {
+            Some(Input::Substack(body)) => std::mem::take(body),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Input {
+    /// A round slot with a primitive shadow of the given type
+    Value(InputType, Expr),
+
+    /// A hexagonal slot, `None` if it is left empty
+    Boolean(Option<Expr>),
+
+    /// A shadow block that can't be covered, like a dropdown menu
+    Shadow(Block),
+
+    /// The blocks inside of a C block
+    Substack(Vec<Block>),
+
+    /// A broadcast menu, stored as a primitive
+    Broadcast(Name, Id),
+}
+
+#[derive(Debug)]
+pub enum Expr {
+    Literal(Value),
+    Variable(Name, Id),
+    List(Name, Id),
+    Block(Box<Block>),
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub value: Value,
+
+    /// The ID of the referred variable, list or broadcast
+    pub id: Option<Id>,
+}
+
+impl Field {
+    pub fn new(value: impl Into<String>) -> Field {
+        Field {
+            value: Value::String(value.into()),
+            id: None,
+        }
+    }
+}
@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+
+use scratch_sb3::block::Mutation;
+use scratch_sb3::{Id, IdGenerator, Name, Number, Value};
+
+use super::ir::{Block, Expr, Field, Input, Script};
+use super::{MATCH_VARIABLE, Names};
+use crate::analysis::SymbolKind;
+use crate::catalog::{self, ArgSpec, FieldSpec, InputType};
+use crate::parsing::{
+    self, Arg, BinOp, BlockCall, ExprKind, FieldKind, Ident, ParamKind, ScriptKind, Signature,
+    SignaturePart, Stmt, StmtKind,
+};
+
+/// A custom block as Scratch sees it
+#[derive(Debug)]
+pub(super) struct Procedure {
+    parts: Vec<ProcedurePart>,
+    argument_ids: Vec<Id>,
+}
+
+#[derive(Debug)]
+enum ProcedurePart {
+    Word(String),
+    Param(Name, ParamKind),
+}
+
+impl Procedure {
+    pub(super) fn new(signature: &Signature, ids: &mut IdGenerator) -> Procedure {
+        let mut parts = Vec::new();
+        let mut argument_ids = Vec::new();
+        for part in &signature.parts {
+            match part {
+                SignaturePart::Label(label) => parts.extend(
+                    label
+                        .split('_')
+                        .filter(|word| !word.is_empty())
+                        .map(|word| ProcedurePart::Word(word.to_string())),
+                ),
+                SignaturePart::Param(param) => {
+                    parts.push(ProcedurePart::Param(param.name.to_string(), param.kind));
+                    argument_ids.push(ids.generate());
+                }
+            }
+        }
+
+        Procedure {
+            parts,
+            argument_ids,
+        }
+    }
+
+    fn call_mutation(&self) -> Mutation {
+        let (name, rest) = self.split_name();
+        let mut builder = Mutation::builder().procedure_call(name);
+        let mut argument_ids = self.argument_ids.iter();
+        for part in rest {
+            builder = match part {
+                ProcedurePart::Word(word) => builder.add_label(word),
+                ProcedurePart::Param(_, ParamKind::Reporter) => {
+                    builder.add_strnum_argument(*argument_ids.next().unwrap())
+                }
+                ProcedurePart::Param(_, ParamKind::Boolean) => {
+                    builder.add_bool_argument(*argument_ids.next().unwrap())
+                }
+            };
+        }
+        builder.build()
+    }
+
+    fn prototype_mutation(&self) -> Mutation {
+        let (name, rest) = self.split_name();
+        let mut builder = Mutation::builder().procedure_prototype(name);
+        let mut argument_ids = self.argument_ids.iter();
+        for part in rest {
+            builder = match part {
+                ProcedurePart::Word(word) => builder.add_label(word),
+                ProcedurePart::Param(name, ParamKind::Reporter) => builder.add_strnum_argument(
+                    *argument_ids.next().unwrap(),
+                    name.clone(),
+                    String::new(),
+                ),
+                ProcedurePart::Param(name, ParamKind::Boolean) => {
+                    builder.add_bool_argument(*argument_ids.next().unwrap(), name.clone(), false)
+                }
+            };
+        }
+        builder.build()
+    }
+
+    /// Split off the first word, which starts the proccode
+    fn split_name(&self) -> (&str, &[ProcedurePart]) {
+        match self.parts.split_first() {
+            Some((ProcedurePart::Word(word), rest)) => (word, rest),
+            _ => ("", &self.parts),
+        }
+    }
+
+    fn params(&self) -> impl Iterator<Item = (&Name, ParamKind, Id)> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                ProcedurePart::Word(_) => None,
+                ProcedurePart::Param(name, kind) => Some((name, *kind)),
+            })
+            .zip(&self.argument_ids)
+            .map(|((name, kind), id)| (name, kind, *id))
+    }
+}
+
+/// Turns analyzed scripts into [IR scripts](Script)
+///
+/// Everything is expected to be checked by the analysis already.
+pub(super) struct Lowerer<'a> {
+    pub(super) names: &'a Names,
+    pub(super) procedures: &'a HashMap<String, Procedure>,
+    pub(super) params: HashMap<Ident, ParamKind>,
+}
+
+impl Lowerer<'_> {
+    pub(super) fn script(&mut self, script: &parsing::Script) -> Script {
+        self.params.clear();
+
+        let top = match &script.kind {
+            ScriptKind::When(hat) => self.builtin(hat),
+            ScriptKind::Def(signature) => {
+                for param in signature.params() {
+                    self.params.insert(param.name.clone(), param.kind);
+                }
+                self.definition(signature)
+            }
+        };
+
+        Script {
+            top,
+            body: self.body(&script.body),
+        }
+    }
+
+    fn definition(&self, signature: &Signature) -> Block {
+        let procedure = &self.procedures[&signature.pattern()];
+
+        let mut prototype = Block::new("procedures_prototype");
+        prototype.mutation = Some(procedure.prototype_mutation());
+        for (name, kind, id) in procedure.params() {
+            prototype
+                .inputs
+                .push((id.to_string(), Input::Shadow(argument_reporter(name, kind))));
+        }
+
+        let mut definition = Block::new("procedures_definition");
+        definition
+            .inputs
+            .push(("custom_block".to_string(), Input::Shadow(prototype)));
+        definition
+    }
+
+    fn body(&self, body: &[Stmt]) -> Vec<Block> {
+        let mut blocks = Vec::with_capacity(body.len());
+        for stmt in body {
+            self.stmt(stmt, &mut blocks);
+        }
+        blocks
+    }
+
+    /// Lower a statement into one or more blocks appended to `blocks`
+    fn stmt(&self, stmt: &Stmt, blocks: &mut Vec<Block>) {
+        let block = match &stmt.kind {
+            StmtKind::Call(call) if call.namespace.is_none() => self.procedure_call(call),
+            StmtKind::Call(call) => self.builtin(call),
+            StmtKind::If {
+                cond,
+                then,
+                otherwise,
+            } => if_else(
+                self.expr(cond),
+                self.body(then),
+                otherwise.as_ref().map(|otherwise| self.body(otherwise)),
+            ),
+            StmtKind::Repeat { times, body } => repeat(self.expr(times), self.body(body)),
+            StmtKind::RepeatUntil { cond, body } => repeat_until(self.expr(cond), self.body(body)),
+            StmtKind::While { cond, body } => {
+                let mut not = Block::new("operator_not");
+                not.inputs
+                    .push(("OPERAND".to_string(), Input::Boolean(Some(self.expr(cond)))));
+                repeat_until(Expr::Block(Box::new(not)), self.body(body))
+            }
+            StmtKind::Match {
+                value,
+                arms,
+                default,
+            } => {
+                // store the value unless reading it again gives the same result,
+                // so it is evaluated only once instead of for every pattern
+                let stored = match &value.kind {
+                    ExprKind::Literal(_) | ExprKind::Ident(_) => None,
+                    _ => {
+                        let (name, id) = self
+                            .names
+                            .get(
+                                SymbolKind::Variable,
+                                &Ident::new(MATCH_VARIABLE.to_string()),
+                            )
+                            .expect("the match variable is declared when needed");
+
+                        let mut set = Block::new("data_setvariableto");
+                        set.inputs.push((
+                            "VALUE".to_string(),
+                            Input::Value(InputType::Text, self.expr(value)),
+                        ));
+                        set.fields.push((
+                            "VARIABLE",
+                            Field {
+                                value: Value::String(name.clone()),
+                                id: Some(*id),
+                            },
+                        ));
+                        blocks.push(set);
+                        Some((name, *id))
+                    }
+                };
+                let value = || match stored {
+                    Some((name, id)) => Expr::Variable(name.clone(), id),
+                    None => self.expr(value),
+                };
+
+                // build the `else if` chain from its innermost branch outwards
+                let mut otherwise = default.as_ref().map(|default| self.body(default));
+                for arm in arms.iter().rev() {
+                    let cond = arm
+                        .patterns
+                        .iter()
+                        .map(|pattern| {
+                            binary(
+                                "operator_equals",
+                                ["OPERAND1", "OPERAND2"],
+                                InputType::Text,
+                                value(),
+                                self.expr(pattern),
+                            )
+                        })
+                        .reduce(|lhs, rhs| {
+                            let mut or = Block::new("operator_or");
+                            or.inputs
+                                .push(("OPERAND1".to_string(), Input::Boolean(Some(lhs))));
+                            or.inputs
+                                .push(("OPERAND2".to_string(), Input::Boolean(Some(rhs))));
+                            Expr::Block(Box::new(or))
+                        })
+                        .expect("match arms have at least one pattern");
+
+                    let if_else = if_else(cond, self.body(&arm.body), otherwise);
+                    otherwise = Some(vec![if_else]);
+                }
+
+                blocks.extend(otherwise.unwrap_or_default());
+                return;
+            }
+            StmtKind::Forever(body) => {
+                let mut block = Block::new("control_forever");
+                block
+                    .inputs
+                    .push(("SUBSTACK".to_string(), Input::Substack(self.body(body))));
+                block
+            }
+            StmtKind::For {
+                var,
+                from,
+                to,
+                body,
+                ..
+            } => {
+                let (name, id) = self
+                    .names
+                    .get(SymbolKind::Variable, var)
+                    .expect("counters are resolved by the analysis");
+                let counter = || Field {
+                    value: Value::String(name.clone()),
+                    id: Some(*id),
+                };
+
+                let mut set = Block::new("data_setvariableto");
+                set.inputs.push((
+                    "VALUE".to_string(),
+                    Input::Value(InputType::Text, self.expr(from)),
+                ));
+                set.fields.push(("VARIABLE", counter()));
+                blocks.push(set);
+
+                // count from the counter unless that's known, so `from` is evaluated only once
+                let start = match &from.kind {
+                    ExprKind::Literal(value) => Expr::Literal(value.clone()),
+                    _ => Expr::Variable(name.clone(), *id),
+                };
+                let one = || Expr::Literal(Value::Number(Number::Integer(1)));
+                let times = binary(
+                    "operator_add",
+                    ["NUM1", "NUM2"],
+                    InputType::Number,
+                    binary(
+                        "operator_subtract",
+                        ["NUM1", "NUM2"],
+                        InputType::Number,
+                        self.expr(to),
+                        start,
+                    ),
+                    one(),
+                );
+
+                let mut change = Block::new("data_changevariableby");
+                change
+                    .inputs
+                    .push(("VALUE".to_string(), Input::Value(InputType::Number, one())));
+                change.fields.push(("VARIABLE", counter()));
+
+                let mut body = self.body(body);
+                body.push(change);
+                repeat(times, body)
+            }
+        };
+        blocks.push(block);
+    }
+
+    fn procedure_call(&self, call: &BlockCall) -> Block {
+        let procedure = &self.procedures[&call.pattern()];
+
+        let mut block = Block::new("procedures_call");
+        block.mutation = Some(procedure.call_mutation());
+        for (arg, (_, _, id)) in call.args().zip(procedure.params()) {
+            let input = match arg {
+                Arg::Reporter(expr) => Input::Value(InputType::Text, self.expr(expr)),
+                Arg::Boolean(expr) => Input::Boolean(Some(self.expr(expr))),
+                Arg::Field(_) => unreachable!("custom blocks don't take fields"),
+            };
+            block.inputs.push((id.to_string(), input));
+        }
+        block
+    }
+
+    fn builtin(&self, call: &BlockCall) -> Block {
+        let namespace = call
+            .namespace
+            .as_ref()
+            .expect("builtin blocks have a namespace");
+        let spec = catalog::lookup(namespace.as_str(), &call.pattern())
+            .expect("unknown blocks are reported by the analysis");
+
+        let mut block = Block::new(spec.opcode);
+        for (arg, arg_spec) in call.args().zip(spec.args) {
+            match (arg, arg_spec) {
+                (Arg::Reporter(expr), ArgSpec::Input { name, ty }) => block
+                    .inputs
+                    .push((name.to_string(), Input::Value(*ty, self.expr(expr)))),
+                (Arg::Boolean(expr), ArgSpec::Boolean { name }) => block
+                    .inputs
+                    .push((name.to_string(), Input::Boolean(Some(self.expr(expr))))),
+                (
+                    Arg::Field(field),
+                    ArgSpec::Menu {
+                        input,
+                        opcode,
+                        field: spec,
+                    },
+                ) => {
+                    let mut menu = Block::new(opcode);
+                    menu.fields.push((spec.name, self.field(field, spec)));
+                    block.inputs.push((input.to_string(), Input::Shadow(menu)));
+                }
+                (Arg::Field(field), ArgSpec::Field(spec)) => {
+                    block.fields.push((spec.name, self.field(field, spec)))
+                }
+                (Arg::Field(field), ArgSpec::Primitive(spec)) => {
+                    let Field {
+                        value: Value::String(name),
+                        id: Some(id),
+                    } = self.field(field, spec)
+                    else {
+                        unreachable!("broadcasts are declared before generating blocks");
+                    };
+                    block
+                        .inputs
+                        .push((spec.name.to_string(), Input::Broadcast(name, id)));
+                }
+                _ => unreachable!("pattern of the call matches the block spec"),
+            }
+        }
+
+        if spec.opcode == "control_stop"
+            && let Some((
+                _,
+                Field {
+                    value: Value::String(option),
+                    ..
+                },
+            )) = block.fields.first()
+        {
+            let hasnext = !catalog::stops_script(option);
+            block.mutation = Some(Mutation::builder().control_stop(hasnext));
+        }
+        block
+    }
+
+    fn field(&self, field: &parsing::Field, spec: &FieldSpec) -> Field {
+        let (name, option) = match &field.kind {
+            FieldKind::Ident(name) => (name.as_str(), spec.option_for_ident(name.as_str())),
+            FieldKind::String(string) => (string.as_str(), spec.option_for_str(string)),
+        };
+
+        if let Some(option) = option {
+            return Field::new(option);
+        }
+
+        let referred = spec
+            .refers
+            .and_then(|kind| self.names.get(kind, &Ident::new(name.to_string())));
+        match referred {
+            Some((name, id)) => Field {
+                value: Value::String(name.clone()),
+                id: Some(*id),
+            },
+            None => Field::new(name),
+        }
+    }
+
+    fn expr(&self, expr: &parsing::Expr) -> Expr {
+        match &expr.kind {
+            ExprKind::Literal(value) => Expr::Literal(value.clone()),
+            ExprKind::Ident(name) => {
+                if let Some(kind) = self.params.get(name) {
+                    Expr::Block(Box::new(argument_reporter(name.as_str(), *kind)))
+                } else if let Some((name, id)) = self.names.get(SymbolKind::Variable, name) {
+                    Expr::Variable(name.clone(), *id)
+                } else if let Some((name, id)) = self.names.get(SymbolKind::List, name) {
+                    Expr::List(name.clone(), *id)
+                } else {
+                    unreachable!("identifiers are resolved by the analysis and constants inlined")
+                }
+            }
+            ExprKind::Call(call) => Expr::Block(Box::new(self.builtin(call))),
+            ExprKind::Binary(op, lhs, rhs) => {
+                let (opcode, names, ty) = match op {
+                    BinOp::Add => ("operator_add", ["NUM1", "NUM2"], InputType::Number),
+                    BinOp::Sub => ("operator_subtract", ["NUM1", "NUM2"], InputType::Number),
+                    BinOp::Mul => ("operator_multiply", ["NUM1", "NUM2"], InputType::Number),
+                    BinOp::Div => ("operator_divide", ["NUM1", "NUM2"], InputType::Number),
+                    BinOp::Greater => ("operator_gt", ["OPERAND1", "OPERAND2"], InputType::Text),
+                    BinOp::Less => ("operator_lt", ["OPERAND1", "OPERAND2"], InputType::Text),
+                    BinOp::Equals => ("operator_equals", ["OPERAND1", "OPERAND2"], InputType::Text),
+                };
+
+                binary(opcode, names, ty, self.expr(lhs), self.expr(rhs))
+            }
+        }
+    }
+}
+
+fn binary(opcode: &'static str, names: [&str; 2], ty: InputType, lhs: Expr, rhs: Expr) -> Expr {
+    let mut block = Block::new(opcode);
+    block
+        .inputs
+        .push((names[0].to_string(), Input::Value(ty, lhs)));
+    block
+        .inputs
+        .push((names[1].to_string(), Input::Value(ty, rhs)));
+    Expr::Block(Box::new(block))
+}
+
+fn if_else(cond: Expr, then: Vec<Block>, otherwise: Option<Vec<Block>>) -> Block {
+    let opcode = match otherwise {
+        Some(_) => "control_if_else",
+        None => "control_if",
+    };
+
+    let mut block = Block::new(opcode);
+    block
+        .inputs
+        .push(("CONDITION".to_string(), Input::Boolean(Some(cond))));
+    block
+        .inputs
+        .push(("SUBSTACK".to_string(), Input::Substack(then)));
+    if let Some(otherwise) = otherwise {
+        block
+            .inputs
+            .push(("SUBSTACK2".to_string(), Input::Substack(otherwise)));
+    }
+    block
+}
+
+fn repeat(times: Expr, body: Vec<Block>) -> Block {
+    let mut block = Block::new("control_repeat");
+    block.inputs.push((
+        "TIMES".to_string(),
+        Input::Value(InputType::PositiveInteger, times),
+    ));
+    block
+        .inputs
+        .push(("SUBSTACK".to_string(), Input::Substack(body)));
+    block
+}
+
+fn repeat_until(cond: Expr, body: Vec<Block>) -> Block {
+    let mut block = Block::new("control_repeat_until");
+    block
+        .inputs
+        .push(("CONDITION".to_string(), Input::Boolean(Some(cond))));
+    block
+        .inputs
+        .push(("SUBSTACK".to_string(), Input::Substack(body)));
+    block
+}
+
+fn argument_reporter(name: &str, kind: ParamKind) -> Block {
+    let opcode = match kind {
+        ParamKind::Reporter => "argument_reporter_string_number",
+        ParamKind::Boolean => "argument_reporter_boolean",
+    };
+
+    let mut block = Block::new(opcode);
+    block.fields.push(("VALUE", Field::new(name)));
+    block
+}
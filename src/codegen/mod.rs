@@ -0,0 +1,664 @@
+//! Generation of Scratch blocks from analyzed scripts
+
+mod emit;
+mod ir;
+mod lower;
+mod optimize;
+
+use std::collections::HashMap;
+
+use scratch_sb3::block::Block;
+use scratch_sb3::{Id, IdGenerator, Name};
+
+use crate::analysis::SymbolKind;
+use crate::parsing::{ExprKind, Ident, Script, ScriptKind, Stmt, StmtKind};
+use lower::{Lowerer, Procedure};
+
+/// How much effort is put into making the generated blocks smaller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptLevel {
+    /// Generate blocks exactly as written
+    None,
+
+    /// Fold expressions of literals into a single literal if the result is exact
+    Fold,
+
+    /// Also remove branches that are never taken and unused custom blocks
+    #[default]
+    Full,
+}
+
+impl From<u8> for OptLevel {
+    fn from(level: u8) -> Self {
+        match level {
+            0 => OptLevel::None,
+            1 => OptLevel::Fold,
+            _ => OptLevel::Full,
+        }
+    }
+}
+
+/// The Scratch names and IDs of the variables, lists and broadcasts a target can refer to
+#[derive(Debug, Default, Clone)]
+pub struct Names {
+    names: HashMap<(SymbolKind, Ident), (Name, Id)>,
+}
+
+impl Names {
+    pub fn insert(&mut self, kind: SymbolKind, ident: Ident, name: Name, id: Id) {
+        self.names.insert((kind, ident), (name, id));
+    }
+
+    pub fn get(&self, kind: SymbolKind, ident: &Ident) -> Option<&(Name, Id)> {
+        self.names.get(&(kind, ident.clone()))
+    }
+}
+
+/// The name of the hidden variable `match` statements store their value in
+///
+/// The stage has to declare it under this name if any target [needs it](needs_match_variable).
+pub const MATCH_VARIABLE: &str = "scrapt match value";
+
+/// Whether any of `scripts` has a `match` statement whose value has to be stored in [`MATCH_VARIABLE`]
+pub fn needs_match_variable<'a>(scripts: impl IntoIterator<Item = &'a Script>) -> bool {
+    fn any(body: &[Stmt]) -> bool {
+        body.iter().any(|stmt| match &stmt.kind {
+            StmtKind::Call(_) => false,
+            StmtKind::If {
+                then, otherwise, ..
+            } => any(then) || otherwise.as_deref().is_some_and(any),
+            StmtKind::Repeat { body: inner, .. }
+            | StmtKind::RepeatUntil { body: inner, .. }
+            | StmtKind::While { body: inner, .. }
+            | StmtKind::Forever(inner)
+            | StmtKind::For { body: inner, .. } => any(inner),
+            StmtKind::Match {
+                value,
+                arms,
+                default,
+            } => {
+                !matches!(value.kind, ExprKind::Literal(_) | ExprKind::Ident(_))
+                    || arms.iter().any(|arm| any(&arm.body))
+                    || default.as_deref().is_some_and(any)
+            }
+        })
+    }
+
+    scripts.into_iter().any(|script| any(&script.body))
+}
+
+/// Generate the blocks of a target from its scripts
+///
+/// `scripts` have to be analyzed without errors and their constants inlined.
+/// Definitions of imported custom blocks are simply part of `scripts`.
+pub fn generate<'a>(
+    scripts: impl IntoIterator<Item = &'a Script>,
+    names: &Names,
+    ids: &mut IdGenerator,
+    opt_level: OptLevel,
+) -> Vec<(Id, Block)> {
+    let scripts: Vec<&Script> = scripts.into_iter().collect();
+
+    let mut procedures = HashMap::new();
+    for script in &scripts {
+        if let ScriptKind::Def(signature) = &script.kind {
+            procedures.insert(signature.pattern(), Procedure::new(signature, ids));
+        }
+    }
+
+    let mut lowerer = Lowerer {
+        names,
+        procedures: &procedures,
+        params: HashMap::new(),
+    };
+    let mut lowered = scripts
+        .into_iter()
+        .map(|script| lowerer.script(script))
+        .collect();
+
+    optimize::optimize(&mut lowered, opt_level);
+    emit::emit(lowered, ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis;
+    use crate::parsing;
+    use scratch_sb3::block::{
+        FullBlock, Input, MutationType, PrimitiveBlock, ReferenceType, ShadowType, ValueType,
+    };
+    use scratch_sb3::{IdOrPrimitiveBlock, Number, Value};
+
+    /// Generate the blocks of a stage, giving its variables, lists and broadcasts the first IDs
+    fn generate_source(source: &str, opt_level: OptLevel) -> Vec<(Id, FullBlock)> {
+        let mut file = parsing::parse(source).expect("source should parse");
+        let (symbols, diagnostics) = analysis::analyze(&file, None, &[], &[], &[]);
+        assert!(diagnostics.errors.is_empty(), "{:?}", diagnostics.errors);
+        analysis::inline_constants(&mut file, &symbols);
+
+        let mut ids = IdGenerator::new();
+        let mut names = Names::default();
+        let headers = &file.headers;
+        let declared = headers
+            .vars
+            .keys()
+            .map(|name| (SymbolKind::Variable, name))
+            .chain(headers.lists.keys().map(|name| (SymbolKind::List, name)))
+            .chain(
+                headers
+                    .broadcasts
+                    .keys()
+                    .map(|name| (SymbolKind::Broadcast, name)),
+            );
+        for (kind, name) in declared {
+            names.insert(kind, name.clone(), name.to_string(), ids.generate());
+        }
+        if needs_match_variable(&file.scripts) {
+            let name = MATCH_VARIABLE.to_string();
+            let ident = Ident::new(name.clone());
+            names.insert(SymbolKind::Variable, ident, name, ids.generate());
+        }
+
+        generate(&file.scripts, &names, &mut ids, opt_level)
+            .into_iter()
+            .map(|(id, block)| match block {
+                Block::Full(block) => (id, *block),
+                Block::Primitive(_) => panic!("only full blocks are generated"),
+            })
+            .collect()
+    }
+
+    /// The opcodes of `blocks` in alphabetical order
+    fn opcodes(blocks: &[(Id, FullBlock)]) -> Vec<&str> {
+        let mut opcodes: Vec<&str> = blocks
+            .iter()
+            .map(|(_, block)| block.opcode.as_str())
+            .collect();
+        opcodes.sort();
+        opcodes
+    }
+
+    fn find<'a>(blocks: &'a [(Id, FullBlock)], opcode: &str) -> &'a FullBlock {
+        blocks
+            .iter()
+            .map(|(_, block)| block)
+            .find(|block| block.opcode == opcode)
+            .unwrap_or_else(|| panic!("no {opcode} block"))
+    }
+
+    fn literal(input: &Input) -> Option<&Value> {
+        match input {
+            Input::Simple(
+                ShadowType::Shadow,
+                IdOrPrimitiveBlock::Primitive(PrimitiveBlock::Simple(_, value)),
+            ) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn constant_folding() {
+        let source = r#"
+            const { width = 240; }
+
+            when events::flag {
+                looks::say_(2 * width + 1);
+            }
+            "#;
+
+        let blocks = generate_source(source, OptLevel::Fold);
+        assert_eq!(opcodes(&blocks), ["event_whenflagclicked", "looks_say"]);
+        assert!(matches!(
+            &find(&blocks, "looks_say").inputs["MESSAGE"],
+            Input::Simple(
+                ShadowType::Shadow,
+                IdOrPrimitiveBlock::Primitive(PrimitiveBlock::Simple(
+                    ValueType::Text,
+                    Value::Number(Number::Integer(481))
+                ))
+            )
+        ));
+
+        let blocks = generate_source(source, OptLevel::None);
+        assert_eq!(
+            opcodes(&blocks),
+            [
+                "event_whenflagclicked",
+                "looks_say",
+                "operator_add",
+                "operator_multiply",
+            ]
+        );
+
+        // only exact results are folded, Scratch computes everything else with 64-bit floats
+        for inexact in ["123456789 * 100", "1 / 3", "0.1 + 0.2"] {
+            let source = format!("when events::flag {{ looks::say_({inexact}); }}");
+            let blocks = generate_source(&source, OptLevel::Fold);
+            assert_eq!(opcodes(&blocks).len(), 3, "{inexact}");
+        }
+    }
+
+    #[test]
+    fn dead_code_elimination() {
+        let source = r#"
+            def unused {
+                looks::say_("never");
+            }
+
+            def used {
+                looks::think_("hmm");
+            }
+
+            when events::flag {
+                if <1 = 2> {
+                    looks::say_("never");
+                } else {
+                    used;
+                }
+                repeat (0) {
+                    looks::say_("never");
+                }
+            }
+            "#;
+
+        let blocks = generate_source(source, OptLevel::Full);
+        assert_eq!(
+            opcodes(&blocks),
+            [
+                "event_whenflagclicked",
+                "looks_think",
+                "procedures_call",
+                "procedures_definition",
+                "procedures_prototype",
+            ]
+        );
+
+        let blocks = generate_source(source, OptLevel::Fold);
+        assert_eq!(
+            blocks
+                .iter()
+                .filter(|(_, block)| block.opcode == "procedures_definition")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn loops() {
+        let source = r#"
+            vars { i; }
+
+            when events::flag {
+                for i in 1..10 {
+                    looks::say_(i);
+                }
+                while <i greater 5> {}
+            }
+            "#;
+
+        let blocks = generate_source(source, OptLevel::Fold);
+        assert_eq!(
+            opcodes(&blocks),
+            [
+                "control_repeat",
+                "control_repeat_until",
+                "data_changevariableby",
+                "data_setvariableto",
+                "event_whenflagclicked",
+                "looks_say",
+                "operator_gt",
+                "operator_not",
+            ]
+        );
+
+        let repeat = find(&blocks, "control_repeat");
+        assert!(matches!(
+            &repeat.inputs["TIMES"],
+            Input::Simple(
+                ShadowType::Shadow,
+                IdOrPrimitiveBlock::Primitive(PrimitiveBlock::Simple(
+                    ValueType::PositiveInteger,
+                    Value::Number(Number::Integer(10))
+                ))
+            )
+        ));
+        assert!(repeat.inputs.contains_key("SUBSTACK"));
+
+        let counter = IdGenerator::new().generate();
+        let change = find(&blocks, "data_changevariableby");
+        assert_eq!(change.fields["VARIABLE"].1, Some(counter));
+    }
+
+    #[test]
+    fn conditional_chains() {
+        let source = r#"
+            vars { x; }
+
+            when events::flag {
+                match (x) {
+                    1, 2 => { looks::say_("few"); }
+                    3 => { looks::say_("three"); }
+                    _ => { looks::say_("many"); }
+                }
+            }
+
+            when events::flag {
+                if <x = 1> {
+                    looks::say_("few");
+                } else if <x = 3> {
+                    looks::say_("three");
+                } else {
+                    looks::say_("many");
+                }
+            }
+            "#;
+
+        let blocks = generate_source(source, OptLevel::None);
+        let said = |id: &IdOrPrimitiveBlock| {
+            let IdOrPrimitiveBlock::Id(id) = id else {
+                panic!("expected a block");
+            };
+            let (_, say) = blocks.iter().find(|(block_id, _)| block_id == id).unwrap();
+            literal(&say.inputs["MESSAGE"]).cloned()
+        };
+
+        // both scripts result in the same chain of two blocks
+        let outer: Vec<&FullBlock> = blocks
+            .iter()
+            .map(|(_, block)| block)
+            .filter(|block| {
+                block.opcode == "control_if_else"
+                    && blocks.iter().any(|(id, hat)| {
+                        hat.opcode == "event_whenflagclicked" && block.parent == Some(*id)
+                    })
+            })
+            .collect();
+        assert_eq!(outer.len(), 2);
+
+        for block in outer {
+            let Input::Simple(ShadowType::NoShadow, IdOrPrimitiveBlock::Id(inner)) =
+                &block.inputs["SUBSTACK2"]
+            else {
+                panic!("expected a nested block");
+            };
+            let (_, inner) = blocks.iter().find(|(id, _)| id == inner).unwrap();
+            assert_eq!(inner.opcode, "control_if_else");
+
+            let Input::Simple(ShadowType::NoShadow, then) = &inner.inputs["SUBSTACK"] else {
+                panic!("expected a substack");
+            };
+            let Input::Simple(ShadowType::NoShadow, otherwise) = &inner.inputs["SUBSTACK2"] else {
+                panic!("expected a substack");
+            };
+            assert_eq!(said(then), Some(Value::String("three".to_string())));
+            assert_eq!(said(otherwise), Some(Value::String("many".to_string())));
+        }
+
+        assert_eq!(
+            opcodes(&blocks)
+                .into_iter()
+                .filter(|opcode| opcode.starts_with("operator"))
+                .collect::<Vec<_>>(),
+            [
+                "operator_equals",
+                "operator_equals",
+                "operator_equals",
+                "operator_equals",
+                "operator_equals",
+                "operator_or",
+            ]
+        );
+    }
+
+    #[test]
+    fn match_value_evaluated_once() {
+        let source = r#"
+            when events::flag {
+                match (operator::pick_random_(1)_to_(3)) {
+                    1 => { looks::say_("one"); }
+                    2, 3 => { looks::say_("more"); }
+                }
+            }
+            "#;
+        let file = parsing::parse(source).unwrap();
+        assert!(needs_match_variable(&file.scripts));
+
+        let blocks = generate_source(source, OptLevel::None);
+        let random: Vec<_> = blocks
+            .iter()
+            .filter(|(_, block)| block.opcode == "operator_random")
+            .collect();
+        assert_eq!(random.len(), 1);
+
+        let set = find(&blocks, "data_setvariableto");
+        assert_eq!(
+            set.fields["VARIABLE"].0,
+            Value::String(MATCH_VARIABLE.to_string())
+        );
+        assert!(matches!(
+            &set.inputs["VALUE"],
+            Input::Obscuring(_, IdOrPrimitiveBlock::Id(id), _) if *id == random[0].0
+        ));
+
+        let variable = set.fields["VARIABLE"].1.unwrap();
+        let compared = blocks
+            .iter()
+            .filter(|(_, block)| block.opcode == "operator_equals")
+            .filter(|(_, block)| {
+                matches!(
+                    &block.inputs["OPERAND1"],
+                    Input::Obscuring(
+                        _,
+                        IdOrPrimitiveBlock::Primitive(PrimitiveBlock::Advanced(_, _, id)),
+                        _
+                    ) if *id == variable
+                )
+            })
+            .count();
+        assert_eq!(compared, 3);
+
+        let simple = parsing::parse("vars { x; } when events::flag { match (x) { 1 => {} } }");
+        assert!(!needs_match_variable(&simple.unwrap().scripts));
+    }
+
+    #[test]
+    fn hat_fields() {
+        let source = r#"
+            broadcasts { start; }
+
+            when events::i_receive_[start] {}
+            when events::key_[left_arrow]_pressed {}
+            when microbit::button_[a]_pressed {}
+            "#;
+
+        let blocks = generate_source(source, OptLevel::Full);
+
+        let start = IdGenerator::new().generate();
+        let received = find(&blocks, "event_whenbroadcastreceived");
+        assert_eq!(
+            received.fields["BROADCAST_OPTION"],
+            (Value::String("start".to_string()), Some(start))
+        );
+
+        let pressed = find(&blocks, "event_whenkeypressed");
+        assert_eq!(
+            pressed.fields["KEY_OPTION"],
+            (Value::String("left arrow".to_string()), None)
+        );
+
+        let button = find(&blocks, "microbit_whenButtonPressed");
+        let Input::Simple(ShadowType::Shadow, IdOrPrimitiveBlock::Id(menu)) = &button.inputs["BTN"]
+        else {
+            panic!("expected a menu");
+        };
+        let (_, menu) = blocks.iter().find(|(id, _)| id == menu).unwrap();
+        assert_eq!(menu.opcode, "microbit_menu_buttons");
+        assert!(menu.shadow);
+        assert_eq!(menu.fields["buttons"].0, Value::String("A".to_string()));
+    }
+
+    #[test]
+    fn broadcast_inputs() {
+        let source = r#"
+            broadcasts { start; }
+
+            when events::flag {
+                events::broadcast_[start];
+                events::broadcast_["start"]_and_wait;
+            }
+            "#;
+
+        let blocks = generate_source(source, OptLevel::Full);
+
+        let start = IdGenerator::new().generate();
+        for opcode in ["event_broadcast", "event_broadcastandwait"] {
+            let block = find(&blocks, opcode);
+            let Input::Simple(
+                ShadowType::Shadow,
+                IdOrPrimitiveBlock::Primitive(PrimitiveBlock::Advanced(
+                    ReferenceType::Broadcast,
+                    name,
+                    id,
+                )),
+            ) = &block.inputs["BROADCAST_INPUT"]
+            else {
+                panic!("expected a broadcast primitive");
+            };
+            assert_eq!((name.as_str(), *id), ("start", start));
+        }
+    }
+
+    #[test]
+    fn stop_mutation() {
+        let source = r#"
+            when events::flag {
+                control::stop_[other_scripts_in_stage];
+                control::stop_[this_script];
+            }
+            "#;
+
+        let blocks = generate_source(source, OptLevel::Full);
+
+        let mut stops: Vec<(&Value, bool)> = blocks
+            .iter()
+            .filter(|(_, block)| block.opcode == "control_stop")
+            .map(|(_, block)| {
+                let Some(MutationType::ControlStop(mutation)) =
+                    block.mutation.as_ref().map(|m| &m.mutation_type)
+                else {
+                    panic!("expected a stop mutation");
+                };
+                (&block.fields["STOP_OPTION"].0, mutation.hasnext)
+            })
+            .collect();
+        stops.sort_by_key(|(_, hasnext)| *hasnext);
+
+        assert_eq!(
+            stops,
+            [
+                (&Value::String("this script".to_string()), false),
+                (&Value::String("other scripts in stage".to_string()), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn list_methods() {
+        let source = r#"
+            lists { items; }
+
+            when events::flag {
+                items.push("apple");
+                items.insert(1, "pear");
+                items.remove(items.len());
+                if <items.contains("pear")> {
+                    looks::say_(items[1]);
+                }
+                items.clear();
+            }
+            "#;
+
+        let blocks = generate_source(source, OptLevel::Full);
+        assert_eq!(
+            opcodes(&blocks),
+            [
+                "control_if",
+                "data_addtolist",
+                "data_deletealloflist",
+                "data_deleteoflist",
+                "data_insertatlist",
+                "data_itemoflist",
+                "data_lengthoflist",
+                "data_listcontainsitem",
+                "event_whenflagclicked",
+                "looks_say",
+            ]
+        );
+
+        let items = IdGenerator::new().generate();
+        for (_, block) in blocks.iter().filter(|(_, b)| b.opcode.starts_with("data_")) {
+            assert_eq!(
+                block.fields["LIST"],
+                (Value::String("items".to_string()), Some(items))
+            );
+        }
+
+        let insert = find(&blocks, "data_insertatlist");
+        assert_eq!(
+            literal(&insert.inputs["INDEX"]),
+            Some(&Value::Number(Number::Integer(1)))
+        );
+        assert_eq!(
+            literal(&insert.inputs["ITEM"]),
+            Some(&Value::String("pear".to_string()))
+        );
+    }
+
+    #[test]
+    fn assignments() {
+        let source = r#"
+            vars { score; lives = 3; }
+
+            when events::flag {
+                score = 0;
+                score += lives * 10;
+                lives -= 1;
+                lives -= score;
+                show score;
+                hide lives;
+            }
+            "#;
+
+        let blocks = generate_source(source, OptLevel::Full);
+        assert_eq!(
+            opcodes(&blocks),
+            [
+                "data_changevariableby",
+                "data_changevariableby",
+                "data_changevariableby",
+                "data_hidevariable",
+                "data_setvariableto",
+                "data_showvariable",
+                "event_whenflagclicked",
+                "operator_multiply",
+                "operator_subtract",
+            ]
+        );
+
+        // the variables are declared in no particular order
+        let field = |opcode| &find(&blocks, opcode).fields["VARIABLE"];
+        let (score, score_id) = field("data_setvariableto");
+        let (lives, lives_id) = field("data_hidevariable");
+        assert_eq!(score, &Value::String("score".to_string()));
+        assert_eq!(lives, &Value::String("lives".to_string()));
+        assert!(score_id.is_some() && lives_id.is_some() && score_id != lives_id);
+
+        let decrement = blocks
+            .iter()
+            .map(|(_, block)| block)
+            .filter(|block| block.opcode == "data_changevariableby")
+            .find_map(|block| literal(&block.inputs["VALUE"]));
+        assert_eq!(decrement, Some(&Value::Number(Number::Integer(-1))));
+    }
+}
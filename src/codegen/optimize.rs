@@ -0,0 +1,246 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use scratch_sb3::block::MutationType;
+use scratch_sb3::{Number, Value};
+
+use super::OptLevel;
+use super::ir::{Block, Expr, Input, Script};
+use crate::analysis::arithmetic;
+use crate::parsing::BinOp;
+
+pub(super) fn optimize(scripts: &mut Vec<Script>, level: OptLevel) {
+    if level >= OptLevel::Fold {
+        for script in scripts.iter_mut() {
+            fold_block(&mut script.top);
+            script.body.iter_mut().for_each(fold_block);
+        }
+    }
+
+    if level >= OptLevel::Full {
+        for script in scripts.iter_mut() {
+            script.body = eliminate(std::mem::take(&mut script.body));
+        }
+        remove_unused_procedures(scripts);
+    }
+}
+
+fn fold_block(block: &mut Block) {
+    for (_, input) in &mut block.inputs {
+        match input {
+            Input::Value(_, expr) | Input::Boolean(Some(expr)) => fold_expr(expr),
+            Input::Substack(body) => body.iter_mut().for_each(fold_block),
+            Input::Boolean(None) | Input::Shadow(_) | Input::Broadcast(..) => {}
+        }
+    }
+}
+
+fn fold_expr(expr: &mut Expr) {
+    let Expr::Block(block) = expr else {
+        return;
+    };
+
+    fold_block(block);
+    if let Some(value) = evaluate(block) {
+        *expr = Expr::Literal(value);
+    }
+}
+
+/// The value of a reporter block whose inputs are all literals
+fn evaluate(block: &Block) -> Option<Value> {
+    let op = match block.opcode {
+        "operator_add" => BinOp::Add,
+        "operator_subtract" => BinOp::Sub,
+        "operator_multiply" => BinOp::Mul,
+        "operator_divide" => BinOp::Div,
+        "operator_join" => {
+            let lhs = literal(block.input("STRING1")?)?;
+            let rhs = literal(block.input("STRING2")?)?;
+            return Some(Value::String(format!("{}{}", text(lhs), text(rhs))));
+        }
+        _ => return None,
+    };
+
+    let lhs = number(literal(block.input("NUM1")?)?)?;
+    let rhs = number(literal(block.input("NUM2")?)?)?;
+    arithmetic(op, lhs, rhs).map(Value::Number)
+}
+
+/// The value of a boolean expression if it is known at compile time
+fn truth(expr: Option<&Expr>) -> Option<bool> {
+    // empty boolean slots are false
+    let Some(expr) = expr else {
+        return Some(false);
+    };
+
+    let Expr::Block(block) = expr else {
+        return None;
+    };
+
+    let condition = |name| match block.input(name) {
+        Some(Input::Boolean(expr)) => truth(expr.as_ref()),
+        _ => None,
+    };
+
+    match block.opcode {
+        "operator_not" => condition("OPERAND").map(|b| !b),
+        "operator_and" => Some(condition("OPERAND1")? && condition("OPERAND2")?),
+        "operator_or" => Some(condition("OPERAND1")? || condition("OPERAND2")?),
+        "operator_gt" | "operator_lt" | "operator_equals" => {
+            let lhs = literal(block.input("OPERAND1")?)?;
+            let rhs = literal(block.input("OPERAND2")?)?;
+            let ordering = compare(lhs, rhs)?;
+
+            Some(match block.opcode {
+                "operator_gt" => ordering == Ordering::Greater,
+                "operator_lt" => ordering == Ordering::Less,
+                _ => ordering == Ordering::Equal,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Compare two values like Scratch: numerically if possible, case-insensitive otherwise
+fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (number(lhs), number(rhs)) {
+        (Some(lhs), Some(rhs)) => lhs.partial_cmp(&rhs),
+        _ => Some(text(lhs).to_lowercase().cmp(&text(rhs).to_lowercase())),
+    }
+}
+
+fn literal(input: &Input) -> Option<&Value> {
+    match input {
+        Input::Value(_, Expr::Literal(value)) => Some(value),
+        _ => None,
+    }
+}
+
+fn number(value: &Value) -> Option<Number> {
+    match value {
+        Value::Number(num) => Some(*num),
+        Value::String(string) => {
+            let string = string.trim();
+            string
+                .parse::<i32>()
+                .map(Number::Integer)
+                .or_else(|_| string.parse::<f32>().map(Number::Float))
+                .ok()
+        }
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Number(num) => num.to_string(),
+        Value::String(string) => string.clone(),
+    }
+}
+
+/// Remove branches that are never taken and loops that never run
+fn eliminate(body: Vec<Block>) -> Vec<Block> {
+    let mut result = Vec::with_capacity(body.len());
+    for mut block in body {
+        for (_, input) in &mut block.inputs {
+            if let Input::Substack(body) = input {
+                *body = eliminate(std::mem::take(body));
+            }
+        }
+
+        let condition = || match block.input("CONDITION") {
+            Some(Input::Boolean(expr)) => truth(expr.as_ref()),
+            _ => None,
+        };
+
+        match block.opcode {
+            "control_if" => match condition() {
+                Some(true) => result.extend(block.take_substack("SUBSTACK")),
+                Some(false) => {}
+                None => result.push(block),
+            },
+            "control_if_else" => match condition() {
+                Some(true) => result.extend(block.take_substack("SUBSTACK")),
+                Some(false) => result.extend(block.take_substack("SUBSTACK2")),
+                None => result.push(block),
+            },
+            "control_repeat" => {
+                let times = block
+                    .input("TIMES")
+                    .and_then(literal)
+                    .and_then(number)
+                    .map(|times| f32::from(times).round());
+
+                if times.is_none_or(|times| times >= 1.0) {
+                    result.push(block);
+                }
+            }
+            "control_repeat_until" => {
+                if condition() != Some(true) {
+                    result.push(block);
+                }
+            }
+            _ => result.push(block),
+        }
+    }
+    result
+}
+
+/// Remove custom block definitions that can't be reached from any hat block
+fn remove_unused_procedures(scripts: &mut Vec<Script>) {
+    let mut used = HashSet::new();
+    let mut pending: Vec<&Script> = scripts.iter().filter(|s| definition(s).is_none()).collect();
+
+    while let Some(script) = pending.pop() {
+        let mut calls = Vec::new();
+        collect_calls(&script.body, &mut calls);
+
+        for proccode in calls {
+            if used.insert(proccode.clone()) {
+                pending.extend(
+                    scripts
+                        .iter()
+                        .filter(|s| definition(s) == Some(proccode.as_str())),
+                );
+            }
+        }
+    }
+
+    scripts.retain(|script| definition(script).is_none_or(|proccode| used.contains(proccode)));
+}
+
+/// The proccode of the custom block a script defines
+fn definition(script: &Script) -> Option<&str> {
+    if script.top.opcode != "procedures_definition" {
+        return None;
+    }
+
+    match script.top.input("custom_block") {
+        Some(Input::Shadow(prototype)) => proccode(prototype),
+        _ => None,
+    }
+}
+
+fn proccode(block: &Block) -> Option<&str> {
+    match &block.mutation.as_ref()?.mutation_type {
+        MutationType::Procedure(procedure) => Some(&procedure.proccode),
+        _ => None,
+    }
+}
+
+fn collect_calls(body: &[Block], calls: &mut Vec<String>) {
+    for block in body {
+        if block.opcode == "procedures_call" {
+            calls.extend(proccode(block).map(String::from));
+        }
+
+        for (_, input) in &block.inputs {
+            match input {
+                Input::Substack(body) => collect_calls(body, calls),
+                Input::Value(_, Expr::Block(block)) | Input::Boolean(Some(Expr::Block(block))) => {
+                    collect_calls(std::slice::from_ref(block.as_ref()), calls)
+                }
+                _ => {}
+            }
+        }
+    }
+}
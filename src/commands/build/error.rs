@@ -1,8 +1,9 @@
-use miette::Diagnostic;
+use miette::{Diagnostic, NamedSource};
 use thiserror::Error;
 
+use scrapt::analysis::AnalysisError;
 use scrapt::manifest;
-use scrapt::parsing::ParsingError;
+use scrapt::parsing::{ParsingError, Span};
 
 #[derive(Debug, Error, Diagnostic)]
 pub enum BuildCmdError {
@@ -12,15 +13,54 @@ pub enum BuildCmdError {
     #[error("No valid file at `{0}`")]
     NoValidFileAt(std::path::PathBuf),
 
+    #[error("Sprite '{0}' has no costumes")]
+    #[diagnostic(help("every sprite needs at least one costume"))]
+    NoCostumes(String),
+
     #[error("I/O error")]
     IoError(#[from] std::io::Error),
 
-    #[error("Failed creating the ZIP archive")]
-    ZipError(#[from] zip::result::ZipError),
+    #[error("The generated project is invalid:{}", .0.iter().map(|e| format!("\n  {e}")).collect::<String>())]
+    #[diagnostic(help("this is a bug in scrapt, please report it"))]
+    InvalidProject(Vec<scratch_sb3::validate::ValidationError>),
+
+    #[error("Failed creating the .sb3 archive")]
+    ArchiveError(#[from] scratch_sb3::archive::ArchiveError),
 
     #[error("Couldn't parse TOML manifest")]
     TomlError(#[from] manifest::TomlDeserializationError),
 
     #[error("Parsing failed")]
-    ParsingError(#[related] Vec<ParsingError>, #[source_code] String),
+    ParsingError(
+        #[related] Vec<ParsingError>,
+        #[source_code] NamedSource<String>,
+    ),
+
+    #[error("Semantic analysis failed")]
+    AnalysisError(
+        #[related] Vec<AnalysisError>,
+        #[source_code] NamedSource<String>,
+    ),
+
+    #[error("Imported file '{path}' not found")]
+    ImportNotFound {
+        path: String,
+
+        #[source_code]
+        src: NamedSource<String>,
+
+        #[label("imported here")]
+        span: Span,
+    },
+
+    #[error("Import cycle: {cycle}")]
+    ImportCycle {
+        cycle: String,
+
+        #[source_code]
+        src: NamedSource<String>,
+
+        #[label("this import closes the cycle")]
+        span: Span,
+    },
 }
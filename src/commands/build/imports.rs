@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use miette::NamedSource;
+
+use scrapt::analysis::{
+    self, AnalysisError, AnalysisWarning, BroadcastUsage, Diagnostics, SymbolKind, SymbolTable,
+};
+use scrapt::parsing::{self, Ident, Import, ScrFile, Script};
+
+use super::BuildCmdError;
+
+/// A file imported by at least one other file
+#[derive(Debug)]
+struct Library {
+    file: ScrFile,
+    source: NamedSource<String>,
+    imports: Vec<usize>,
+}
+
+/// Loads `.scr` files together with everything they import
+///
+/// Every imported file is only parsed and analyzed once, no matter how often it is imported.
+#[derive(Debug)]
+pub struct Loader {
+    project_path: PathBuf,
+    libraries: Vec<Library>,
+    loaded: HashMap<PathBuf, usize>,
+
+    /// The names of all sprites in the project
+    sprites: Vec<Ident>,
+
+    /// Allow broadcasts that aren't declared in any `broadcasts` header
+    auto_declare_broadcasts: bool,
+
+    /// The broadcasts each target and library sends and receives, next to its source for diagnostics
+    broadcasts: Vec<(NamedSource<String>, BroadcastUsage)>,
+
+    /// The files that are currently being loaded, used to detect import cycles
+    stack: Vec<PathBuf>,
+}
+
+impl Loader {
+    pub fn new(
+        project_path: PathBuf,
+        sprites: Vec<Ident>,
+        auto_declare_broadcasts: bool,
+    ) -> Loader {
+        Loader {
+            project_path,
+            libraries: Vec::new(),
+            loaded: HashMap::new(),
+            sprites,
+            auto_declare_broadcasts,
+            broadcasts: Vec::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Parse and analyze the file of a target, printing warnings right away
+    ///
+    /// `stage` is the symbol table of the stage if the file belongs to a sprite.
+    /// Besides the file and its symbols, this returns all files it imports, directly or not.
+    pub fn load_target(
+        &mut self,
+        path: &Path,
+        stage: Option<&SymbolTable>,
+    ) -> Result<(ScrFile, SymbolTable, Vec<usize>), BuildCmdError> {
+        let path = path.canonicalize()?;
+        let (mut file, source) = self.parse(&path)?;
+
+        self.stack.push(path.clone());
+        let imports = self.load_imports(&path, &file, &source)?;
+        self.stack.pop();
+
+        let (symbols, mut diagnostics) = analysis::analyze(
+            &file,
+            stage,
+            &self.imported(&file, &imports),
+            &self.indirect(&file, &imports),
+            &self.sprites,
+        );
+        if self.auto_declare_broadcasts {
+            diagnostics.errors.retain(|err| {
+                !matches!(
+                    err,
+                    AnalysisError::UndeclaredSymbol {
+                        kind: SymbolKind::Broadcast,
+                        ..
+                    }
+                )
+            });
+        }
+        report(diagnostics, source.clone())?;
+        self.broadcasts
+            .push((source, BroadcastUsage::new(&file.scripts)));
+
+        analysis::inline_constants(&mut file, &symbols);
+
+        let mut libraries = Vec::new();
+        let mut pending = imports;
+        while let Some(index) = pending.pop() {
+            if !libraries.contains(&index) {
+                libraries.push(index);
+                pending.extend(&self.libraries[index].imports);
+            }
+        }
+
+        Ok((file, symbols, libraries))
+    }
+
+    /// The scripts of a target together with the scripts of the files it imports
+    pub fn scripts<'a>(
+        &'a self,
+        file: &'a ScrFile,
+        libraries: &'a [usize],
+    ) -> impl Iterator<Item = &'a Script> {
+        libraries
+            .iter()
+            .flat_map(|&index| &self.libraries[index].file.scripts)
+            .chain(&file.scripts)
+    }
+
+    /// Check that the broadcasts used by the loaded libraries are declared by any target
+    ///
+    /// This has to wait until all targets are loaded, as libraries are loaded before the files importing them.
+    pub fn check_libraries(&self, declared: &[&Ident]) -> Result<(), BuildCmdError> {
+        if self.auto_declare_broadcasts {
+            return Ok(());
+        }
+
+        for library in &self.libraries {
+            let usage = BroadcastUsage::new(&library.file.scripts);
+            report(
+                analysis::check_library_broadcasts(&usage, declared),
+                library.source.clone(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Print warnings for broadcasts that are sent but never received by any target, or the other way around
+    pub fn report_broadcasts(&self) {
+        let usages: Vec<_> = self.broadcasts.iter().map(|(_, usage)| usage).collect();
+        let diagnostics = analysis::check_broadcasts(&usages);
+        for ((source, _), diagnostics) in self.broadcasts.iter().zip(diagnostics) {
+            print_warnings(diagnostics.warnings, source);
+        }
+    }
+
+    /// The names of all broadcasts that are sent or received by the loaded targets
+    pub fn used_broadcasts(&self) -> impl Iterator<Item = &Ident> {
+        self.broadcasts.iter().flat_map(|(_, usage)| usage.names())
+    }
+
+    fn load_library(&mut self, path: PathBuf) -> Result<usize, BuildCmdError> {
+        if let Some(&index) = self.loaded.get(&path) {
+            return Ok(index);
+        }
+
+        let (file, source) = self.parse(&path)?;
+
+        self.stack.push(path.clone());
+        let imports = self.load_imports(&path, &file, &source)?;
+        self.stack.pop();
+
+        let diagnostics = analysis::analyze_library(
+            &file,
+            &self.imported(&file, &imports),
+            &self.indirect(&file, &imports),
+        );
+        report(diagnostics, source.clone())?;
+        self.broadcasts
+            .push((source.clone(), BroadcastUsage::new(&file.scripts)));
+
+        let index = self.libraries.len();
+        self.libraries.push(Library {
+            file,
+            source,
+            imports,
+        });
+        self.loaded.insert(path, index);
+        Ok(index)
+    }
+
+    /// Load the files imported by `file` and return their indices
+    fn load_imports(
+        &mut self,
+        path: &Path,
+        file: &ScrFile,
+        source: &NamedSource<String>,
+    ) -> Result<Vec<usize>, BuildCmdError> {
+        let dir = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.project_path.clone());
+
+        let mut imports = Vec::new();
+        for import in &file.imports {
+            let Ok(import_path) = dir.join(&import.path).canonicalize() else {
+                return Err(BuildCmdError::ImportNotFound {
+                    path: import.path.clone(),
+                    src: source.clone(),
+                    span: import.span,
+                });
+            };
+
+            if let Some(start) = self.stack.iter().position(|p| *p == import_path) {
+                let cycle: Vec<_> = self.stack[start..]
+                    .iter()
+                    .chain([&import_path])
+                    .map(|p| self.name(p))
+                    .collect();
+
+                return Err(BuildCmdError::ImportCycle {
+                    cycle: cycle.join(" -> "),
+                    src: source.clone(),
+                    span: import.span,
+                });
+            }
+
+            imports.push(self.load_library(import_path)?);
+        }
+
+        Ok(imports)
+    }
+
+    fn imported<'a>(
+        &'a self,
+        file: &'a ScrFile,
+        imports: &[usize],
+    ) -> Vec<(&'a Import, &'a ScrFile)> {
+        file.imports
+            .iter()
+            .zip(imports)
+            .map(|(import, &index)| (import, &self.libraries[index].file))
+            .collect()
+    }
+
+    /// The files imported by the imports of `file`, directly or not, each next to the import they come from
+    fn indirect<'a>(
+        &'a self,
+        file: &'a ScrFile,
+        imports: &[usize],
+    ) -> Vec<(&'a Import, &'a ScrFile)> {
+        let mut seen = imports.to_vec();
+        let mut indirect = Vec::new();
+        for (import, &index) in file.imports.iter().zip(imports) {
+            let mut pending = self.libraries[index].imports.clone();
+            while let Some(index) = pending.pop() {
+                if !seen.contains(&index) {
+                    seen.push(index);
+                    indirect.push((import, &self.libraries[index].file));
+                    pending.extend(&self.libraries[index].imports);
+                }
+            }
+        }
+        indirect
+    }
+
+    fn parse(&self, path: &Path) -> Result<(ScrFile, NamedSource<String>), BuildCmdError> {
+        tracing::debug!("Handle {:?}...", path);
+        let source = NamedSource::new(self.name(path), fs::read_to_string(path)?);
+
+        match parsing::parse(source.inner()) {
+            Ok(file) => Ok((file, source)),
+            Err(errs) => Err(BuildCmdError::ParsingError(errs, source)),
+        }
+    }
+
+    /// The name of a file in diagnostics, relative to the project if possible
+    fn name(&self, path: &Path) -> String {
+        path.strip_prefix(&self.project_path)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+}
+
+/// Print the warnings and fail if there are any errors
+fn report(diagnostics: Diagnostics, source: NamedSource<String>) -> Result<(), BuildCmdError> {
+    let Diagnostics { errors, warnings } = diagnostics;
+    print_warnings(warnings, &source);
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(BuildCmdError::AnalysisError(errors, source))
+    }
+}
+
+fn print_warnings(warnings: Vec<AnalysisWarning>, source: &NamedSource<String>) {
+    for warning in warnings {
+        let report = miette::Report::new(warning).with_source_code(source.clone());
+        eprintln!("{report:?}");
+    }
+}
@@ -1,16 +1,24 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use asset::Asset;
 pub use error::BuildCmdError;
+use imports::Loader;
 
-use scratch_sb3::target::Target;
+use scratch_sb3::block::Block;
+use scratch_sb3::extension::Extension;
+use scratch_sb3::target::{self, List, SpriteBuilder, StageBuilder, Target, Variable};
+use scratch_sb3::validate::ValidationError;
+use scratch_sb3::{Id, IdGenerator, Value};
 
+use scrapt::analysis::SymbolKind;
+use scrapt::codegen::{self, Names, OptLevel};
 use scrapt::manifest::Manifest;
-use scrapt::parsing;
+use scrapt::parsing::{Headers, Ident, SpriteSettings, StageSettings};
 
 mod asset;
 mod error;
+mod imports;
 mod write;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -28,6 +36,7 @@ pub fn build(
     manifest_path: Option<PathBuf>,
     output_file: Option<PathBuf>,
     output_type: OutputType,
+    opt_level: OptLevel,
 ) -> Result<(), BuildCmdError> {
     tracing::info!("Building...");
 
@@ -51,70 +60,165 @@ pub fn build(
         f
     });
 
+    let assets_dir = project_path.join(&manifest_scrapt.assets.directory);
+    let mut ids = IdGenerator::new();
+    let mut loader = Loader::new(
+        project_path.clone(),
+        manifest_scrapt
+            .sprites
+            .values()
+            .cloned()
+            .map(Ident::new)
+            .collect(),
+        manifest_scrapt.project.auto_declare_broadcasts,
+    );
+    let mut assets = Vec::new();
+    let mut extensions = manifest_scrapt.project.extensions.clone();
+
     let stage_path = project_path.join("stage.scr");
-    let stage = fs::read_to_string(&stage_path).unwrap();
+    let (stage_file, stage_symbols, stage_libraries) = loader.load_target(&stage_path, None)?;
 
-    tracing::debug!("Handle {:?}...", stage_path);
-    let headers: parsing::Headers =
-        parsing::parse(&stage).map_err(|errs| BuildCmdError::ParsingError(errs, stage))?;
+    // sprites are layered in the order of the manifest, the first one is at the back
+    let mut sprite_files = Vec::new();
+    for (file_stem, name) in &manifest_scrapt.sprites {
+        let sprite_path = project_path.join(file_stem).with_extension("scr");
+        if !sprite_path.is_file() {
+            return Err(BuildCmdError::NoValidFileAt(sprite_path));
+        }
 
-    let mut s_builder = Target::stage_builder();
-    let mut assets = Vec::new();
+        let (sprite_file, _, sprite_libraries) =
+            loader.load_target(&sprite_path, Some(&stage_symbols))?;
+        sprite_files.push((name, sprite_file, sprite_libraries));
+    }
 
-    for (costume_name, (filetype, path)) in &headers.costumes {
-        let file_name = match path {
-            Some(path) => path.clone(),
-            None => PathBuf::from(costume_name.to_string()).with_extension(filetype.extension()),
-        };
+    // broadcasts are global, so all targets share the names and IDs of the stage
+    let declared_broadcasts: Vec<&Ident> = stage_file
+        .headers
+        .broadcasts
+        .keys()
+        .chain(
+            sprite_files
+                .iter()
+                .flat_map(|(_, file, _)| file.headers.broadcasts.keys()),
+        )
+        .collect();
+    loader.check_libraries(&declared_broadcasts)?;
+    loader.report_broadcasts();
 
-        let path = project_path
-            .join(&manifest_scrapt.assets.directory)
-            .join(file_name);
+    let mut broadcast_names: Vec<&Ident> = declared_broadcasts
+        .iter()
+        .copied()
+        .chain(loader.used_broadcasts())
+        .collect();
+    broadcast_names.sort_by_key(|name| name.as_str());
+    broadcast_names.dedup();
 
-        if !path.is_file() {
-            return Err(BuildCmdError::NoValidFileAt(path));
-        }
+    let mut stage_names = Names::default();
+    let mut broadcasts = Vec::new();
+    for name in broadcast_names {
+        let id = ids.generate();
+        stage_names.insert(SymbolKind::Broadcast, name.clone(), name.to_string(), id);
+        broadcasts.push((id, name.to_string()));
+    }
+
+    let mut stage_data = TargetData::new(
+        &stage_file.headers,
+        &assets_dir,
+        manifest_scrapt.assets.auto_renaming,
+        &mut ids,
+        &mut stage_names,
+        &mut assets,
+    )?;
+
+    // `match` statements of all targets share one global variable to store their value in
+    let all_scripts = loader.scripts(&stage_file, &stage_libraries).chain(
+        sprite_files
+            .iter()
+            .flat_map(|(_, file, libraries)| loader.scripts(file, libraries)),
+    );
+    if codegen::needs_match_variable(all_scripts) {
+        let id = ids.generate();
+        let name = codegen::MATCH_VARIABLE.to_string();
+        stage_names.insert(
+            SymbolKind::Variable,
+            Ident::new(name.clone()),
+            name.clone(),
+            id,
+        );
+        stage_data
+            .variables
+            .push((id, Variable::new(name, Value::Number(0.into()))));
+    }
+
+    stage_data.blocks = codegen::generate(
+        loader.scripts(&stage_file, &stage_libraries),
+        &stage_names,
+        &mut ids,
+        opt_level,
+    );
+    add_extensions(&stage_data.blocks, &mut extensions);
 
-        let asset = Asset::new(path, filetype.extension())?;
-        s_builder = s_builder.add_costume(scratch_sb3::target::Asset::costume(
-            asset.hash.clone(),
-            costume_name.to_string(),
-            asset.filename(manifest_scrapt.assets.auto_renaming)?,
-            filetype.extension().to_string(),
-        ));
-        assets.push(asset);
+    let mut stage_builder = Target::stage_builder();
+    for (id, name) in broadcasts {
+        stage_builder = stage_builder.add_broadcast(id, name);
     }
+    let stage = stage_data
+        .add_to_stage(stage_builder)
+        .volume(99)
+        .current_costume(stage_file.headers.current_costume.map(|i| i as u32));
+    let stage = apply_stage_settings(stage, &stage_file.headers.set).build();
 
-    for (sound_name, (filetype, path)) in &headers.sounds {
-        let file_name = match path {
-            Some(path) => path.clone(),
-            None => PathBuf::from(sound_name.to_string()).with_extension(filetype.extension()),
-        };
+    let mut p_builder = scratch_sb3::Project::builder(stage);
 
-        let path = project_path
-            .join(&manifest_scrapt.assets.directory)
-            .join(file_name);
+    for (layer_order, (name, sprite_file, sprite_libraries)) in (1..).zip(sprite_files) {
+        let mut sprite_names = stage_names.clone();
+        let mut sprite_data = TargetData::new(
+            &sprite_file.headers,
+            &assets_dir,
+            manifest_scrapt.assets.auto_renaming,
+            &mut ids,
+            &mut sprite_names,
+            &mut assets,
+        )?;
+        sprite_data.blocks = codegen::generate(
+            loader.scripts(&sprite_file, &sprite_libraries),
+            &sprite_names,
+            &mut ids,
+            opt_level,
+        );
+        add_extensions(&sprite_data.blocks, &mut extensions);
 
-        if !path.is_file() {
-            return Err(BuildCmdError::NoValidFileAt(path));
+        if sprite_data.costumes.is_empty() {
+            return Err(BuildCmdError::NoCostumes(name.clone()));
         }
 
-        let asset = Asset::new(path, filetype.extension())?;
-        s_builder = s_builder.add_sound(scratch_sb3::target::Asset::sound(
-            asset.hash.clone(),
-            sound_name.to_string(),
-            asset.filename(manifest_scrapt.assets.auto_renaming)?,
-            filetype.extension().to_string(),
-        ));
-        assets.push(asset);
+        let mut sprite_builder = sprite_data
+            .add_to_sprite(Target::sprite_builder(name.clone()))
+            .current_costume(sprite_file.headers.current_costume.map(|i| i as u32))
+            .layer_order(layer_order);
+        if let Some(settings) = &sprite_file.headers.sprite {
+            sprite_builder = apply_sprite_settings(sprite_builder, settings);
+        }
+        p_builder = p_builder.add_sprite(sprite_builder.build());
     }
 
-    let stage = s_builder
-        .volume(99)
-        .current_costume(headers.current_costume.map(|i| i as u32))
-        .build();
+    for extension in extensions {
+        p_builder = p_builder.add_extension(extension);
+    }
+    let scratch_project = p_builder.build();
 
-    let scratch_project = scratch_sb3::Project::builder(stage).build();
+    let errors: Vec<_> = scratch_project
+        .validate()
+        .into_iter()
+        // without renaming, assets keep their file names instead of being named after their hash
+        .filter(|error| {
+            manifest_scrapt.assets.auto_renaming
+                || !matches!(error, ValidationError::AssetFilename { .. })
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(BuildCmdError::InvalidProject(errors));
+    }
 
     match output_type {
         OutputType::Zip => write::write_to_zip(
@@ -133,3 +237,202 @@ pub fn build(
 
     Ok(())
 }
+
+fn apply_stage_settings(mut builder: StageBuilder, settings: &StageSettings) -> StageBuilder {
+    if let Some(tempo) = settings.tempo {
+        builder = builder.tempo(tempo);
+    }
+    if let Some(volume) = settings.volume {
+        builder = builder.volume(volume);
+    }
+    if let Some(video_transparency) = settings.video_transparency {
+        builder = builder.video_transparency(video_transparency);
+    }
+    if let Some(video_state) = settings.video_state {
+        builder = builder.video_state(video_state);
+    }
+    if let Some(language) = settings.text_to_speech_language {
+        builder = builder.text_to_speech_language(language);
+    }
+    builder
+}
+
+fn apply_sprite_settings(mut builder: SpriteBuilder, settings: &SpriteSettings) -> SpriteBuilder {
+    if let Some(x) = settings.x {
+        builder = builder.x(x);
+    }
+    if let Some(y) = settings.y {
+        builder = builder.y(y);
+    }
+    if let Some(size) = settings.size {
+        builder = builder.size(size);
+    }
+    if let Some(direction) = settings.direction {
+        builder = builder.direction(direction);
+    }
+    if let Some(visible) = settings.visible {
+        builder = builder.visible(visible);
+    }
+    if let Some(draggable) = settings.draggable {
+        builder = builder.draggable(draggable);
+    }
+    if let Some(rotation_style) = settings.rotation_style {
+        builder = builder.rotation_style(rotation_style);
+    }
+    builder
+}
+
+/// Add the extensions that `blocks` come from to `extensions`, unless they are listed already
+fn add_extensions(blocks: &[(Id, Block)], extensions: &mut Vec<Extension>) {
+    for (_, block) in blocks {
+        if let Block::Full(block) = block
+            && let Some(extension) = Extension::of_opcode(&block.opcode)
+            && !extensions.contains(&extension)
+        {
+            extensions.push(extension);
+        }
+    }
+}
+
+/// The declarations of a target, ready to be added to its builder
+struct TargetData {
+    variables: Vec<(Id, Variable)>,
+    lists: Vec<(Id, List)>,
+    costumes: Vec<target::Asset>,
+    sounds: Vec<target::Asset>,
+    blocks: Vec<(Id, Block)>,
+}
+
+impl TargetData {
+    fn new(
+        headers: &Headers,
+        assets_dir: &Path,
+        auto_renaming: bool,
+        ids: &mut IdGenerator,
+        names: &mut Names,
+        assets: &mut Vec<Asset>,
+    ) -> Result<TargetData, BuildCmdError> {
+        let mut vars: Vec<_> = headers.vars.iter().collect();
+        vars.sort_by_key(|(name, _)| name.as_str());
+        let variables = vars
+            .into_iter()
+            .map(|(name, decl)| {
+                let value = decl.value.clone().unwrap_or(Value::Number(0.into()));
+                let variable = if decl.cloud {
+                    Variable::cloud(name.to_string(), value)
+                } else {
+                    Variable::new(name.to_string(), value)
+                };
+
+                let id = ids.generate();
+                let (Variable::Simple(scratch_name, _) | Variable::MaybeCloud(scratch_name, _, _)) =
+                    &variable;
+                names.insert(SymbolKind::Variable, name.clone(), scratch_name.clone(), id);
+                (id, variable)
+            })
+            .collect();
+
+        let mut lists: Vec<_> = headers.lists.iter().collect();
+        lists.sort_by_key(|(name, _)| name.as_str());
+        let lists = lists
+            .into_iter()
+            .map(|(name, (values, _))| {
+                let id = ids.generate();
+                names.insert(SymbolKind::List, name.clone(), name.to_string(), id);
+                (id, (name.to_string(), values.clone()))
+            })
+            .collect();
+
+        let mut costumes = Vec::new();
+        for (costume_name, (filetype, path, _)) in &headers.costumes {
+            let file_name = match path {
+                Some(path) => path.clone(),
+                None => {
+                    PathBuf::from(costume_name.to_string()).with_extension(filetype.extension())
+                }
+            };
+
+            let path = assets_dir.join(file_name);
+            if !path.is_file() {
+                return Err(BuildCmdError::NoValidFileAt(path));
+            }
+
+            let asset = Asset::new(path, filetype.extension())?;
+            costumes.push(target::Asset::costume(
+                asset.hash.clone(),
+                costume_name.to_string(),
+                asset.filename(auto_renaming)?,
+                filetype.extension().to_string(),
+            ));
+            assets.push(asset);
+        }
+
+        let mut sounds = Vec::new();
+        for (sound_name, (filetype, path, _)) in &headers.sounds {
+            let file_name = match path {
+                Some(path) => path.clone(),
+                None => PathBuf::from(sound_name.to_string()).with_extension(filetype.extension()),
+            };
+
+            let path = assets_dir.join(file_name);
+            if !path.is_file() {
+                return Err(BuildCmdError::NoValidFileAt(path));
+            }
+
+            let asset = Asset::new(path, filetype.extension())?;
+            sounds.push(target::Asset::sound(
+                asset.hash.clone(),
+                sound_name.to_string(),
+                asset.filename(auto_renaming)?,
+                filetype.extension().to_string(),
+            ));
+            assets.push(asset);
+        }
+
+        Ok(TargetData {
+            variables,
+            lists,
+            costumes,
+            sounds,
+            blocks: Vec::new(),
+        })
+    }
+
+    fn add_to_stage(self, mut builder: StageBuilder) -> StageBuilder {
+        for (id, variable) in self.variables {
+            builder = builder.add_variable(id, variable);
+        }
+        for (id, list) in self.lists {
+            builder = builder.add_list(id, list);
+        }
+        for costume in self.costumes {
+            builder = builder.add_costume(costume);
+        }
+        for sound in self.sounds {
+            builder = builder.add_sound(sound);
+        }
+        for (id, block) in self.blocks {
+            builder = builder.add_block(id, block);
+        }
+        builder
+    }
+
+    fn add_to_sprite(self, mut builder: SpriteBuilder) -> SpriteBuilder {
+        for (id, variable) in self.variables {
+            builder = builder.add_variable(id, variable);
+        }
+        for (id, list) in self.lists {
+            builder = builder.add_list(id, list);
+        }
+        for costume in self.costumes {
+            builder = builder.add_costume(costume);
+        }
+        for sound in self.sounds {
+            builder = builder.add_sound(sound);
+        }
+        for (id, block) in self.blocks {
+            builder = builder.add_block(id, block);
+        }
+        builder
+    }
+}
@@ -2,7 +2,7 @@ use std::fs;
 use std::io::Write;
 use std::path::Path;
 
-use zip::write::{SimpleFileOptions, ZipWriter};
+use scratch_sb3::Sb3Archive;
 
 use super::{BuildCmdError, asset::Asset};
 
@@ -14,21 +14,12 @@ pub fn write_to_zip(
 ) -> Result<(), BuildCmdError> {
     tracing::info!("Writing ZIP file {:?}...", output_path.as_ref());
 
-    let output_file = fs::File::create(output_path)?;
-    let mut zip = ZipWriter::new(output_file);
-    let zip_options = SimpleFileOptions::default();
-
-    zip.start_file("project.json", zip_options)?;
-    zip.write_all(scratch_project.to_json().as_bytes())?;
-
+    let mut archive = Sb3Archive::new(scratch_project);
     for asset in assets {
-        zip.start_file(asset.filename(rename)?, zip_options)?;
-
-        let file = fs::read(&asset.path)?;
-        zip.write_all(&file)?;
+        archive.add_asset(asset.filename(rename)?, fs::read(&asset.path)?);
     }
 
-    zip.finish()?;
+    archive.write(fs::File::create(output_path)?)?;
 
     Ok(())
 }
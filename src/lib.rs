@@ -1,3 +1,6 @@
+pub mod analysis;
+pub mod catalog;
+pub mod codegen;
 pub mod manifest;
 mod media_types;
 pub mod parsing;
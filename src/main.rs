@@ -18,7 +18,13 @@ fn main() -> miette::Result<()> {
                 false => OutputType::Zip,
             };
 
-            commands::build(args.project_path, args.manifest, args.output, output_type)?
+            commands::build(
+                args.project_path,
+                args.manifest,
+                args.output,
+                output_type,
+                args.opt_level.into(),
+            )?
         }
         Cmd::Generate(_) => unimplemented!(),
         Cmd::New(args) => commands::new(args.path)?,
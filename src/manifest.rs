@@ -1,5 +1,4 @@
-use std::collections::HashMap;
-
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 pub use toml::de::Error as TomlDeserializationError;
 
@@ -9,8 +8,9 @@ use scratch_sb3::metadata::Metadata;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Manifest {
     pub project: Project,
+    /// Sprite files and their names, in the order of their layers from back to front
     #[serde(default)]
-    pub sprites: HashMap<String, String>,
+    pub sprites: IndexMap<String, String>,
     #[serde(default)]
     pub assets: Assets,
     #[serde(default)]
@@ -32,6 +32,9 @@ pub struct Project {
     pub name: String,
     #[serde(default)]
     pub extensions: Vec<Extension>,
+    /// Declare every broadcast that is sent or received instead of requiring a `broadcasts` header
+    #[serde(default)]
+    pub auto_declare_broadcasts: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1,26 +1,65 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::str::FromStr;
 
 use chumsky::prelude::*;
 
-use scratch_sb3::Value;
+use scratch_sb3::target::{Language, MAX_CLOUD_VARIABLES, RotationStyle, VideoState};
+use scratch_sb3::{Angle, Number, Percentage, Value};
 
 use super::lexer::Token;
-use super::{Ident, ParseErr, ParseInput, Span};
+use super::scripts::{expr, negate};
+use super::{Expr, Ident, ParseErr, ParseInput, Span, ident, value};
 use crate::media_types::{AudioType, ImgType};
 
-type SetHeader = HashMap<Ident, Value>;
-type VarsHeader = HashMap<Ident, Option<Value>>;
-type ListsHeader = HashMap<Ident, Vec<Value>>;
-type BroadcastsHeader = HashSet<Ident>;
-type CostumesHeader = HashMap<Ident, (ImgType, Option<PathBuf>)>;
-type SoundsHeader = HashMap<Ident, (AudioType, Option<PathBuf>)>;
+type VarsHeader = HashMap<Ident, VarDecl>;
+type ConstsHeader = HashMap<Ident, (Expr, Span)>;
+type ListsHeader = HashMap<Ident, (Vec<Value>, Span)>;
+type BroadcastsHeader = HashMap<Ident, Span>;
+type CostumesHeader = HashMap<Ident, (ImgType, Option<PathBuf>, Span)>;
+type SoundsHeader = HashMap<Ident, (AudioType, Option<PathBuf>, Span)>;
+
+/// A single declaration of the `vars` header
+#[derive(Debug, Clone)]
+pub struct VarDecl {
+    pub value: Option<Value>,
+
+    /// Whether the variable is stored on the Scratch servers
+    pub cloud: bool,
+    pub span: Span,
+}
+
+/// The properties of the stage set by its `set` header, `None` keeps the default
+#[derive(Debug, Clone, Default)]
+pub struct StageSettings {
+    /// Beats per minute of the music extension
+    pub tempo: Option<u32>,
+    pub volume: Option<Percentage>,
+    pub video_transparency: Option<Percentage>,
+    pub video_state: Option<VideoState>,
+    pub text_to_speech_language: Option<Language>,
+}
+
+/// The properties of a sprite set by its `sprite` header, `None` keeps Scratch's default
+#[derive(Debug, Clone)]
+pub struct SpriteSettings {
+    pub x: Option<Number>,
+    pub y: Option<Number>,
+    pub size: Option<Percentage>,
+    pub direction: Option<Angle>,
+    pub visible: Option<bool>,
+    pub draggable: Option<bool>,
+    pub rotation_style: Option<RotationStyle>,
+    pub span: Span,
+}
 
 #[derive(Debug)]
 pub struct Headers {
-    pub set: SetHeader,
+    pub set: StageSettings,
+    pub sprite: Option<SpriteSettings>,
     pub vars: VarsHeader,
+    pub consts: ConstsHeader,
     pub lists: ListsHeader,
     pub broadcasts: BroadcastsHeader,
     pub costumes: CostumesHeader,
@@ -36,7 +75,9 @@ impl Headers {
             .collect::<Vec<_>>()
             .validate(|headers, e, emitter| {
                 let mut set = None;
+                let mut sprite = None;
                 let mut vars = None;
+                let mut consts = None;
                 let mut lists = None;
                 let mut broadcasts = None;
                 let mut costumes = None;
@@ -45,7 +86,9 @@ impl Headers {
                 for header in headers {
                     match header {
                         Header::Set(s) if set.is_none() => set = Some(s),
+                        Header::Sprite(s) if sprite.is_none() => sprite = Some(s),
                         Header::Vars(v) if vars.is_none() => vars = Some(v),
+                        Header::Consts(c) if consts.is_none() => consts = Some(c),
                         Header::Lists(l) if lists.is_none() => lists = Some(l),
                         Header::Broadcasts(b) if broadcasts.is_none() => broadcasts = Some(b),
                         Header::Costumes(c) if costumes.is_none() => costumes = Some(c),
@@ -61,7 +104,9 @@ impl Headers {
 
                 Headers {
                     set: set.unwrap_or_default(),
+                    sprite,
                     vars: vars.unwrap_or_default(),
+                    consts: consts.unwrap_or_default(),
                     lists: lists.unwrap_or_default(),
                     broadcasts: broadcasts.unwrap_or_default(),
                     costumes,
@@ -73,8 +118,10 @@ impl Headers {
 }
 
 enum Header {
-    Set(SetHeader),
+    Set(StageSettings),
+    Sprite(SpriteSettings),
     Vars(VarsHeader),
+    Consts(ConstsHeader),
     Lists(ListsHeader),
     Broadcasts(BroadcastsHeader),
     Costumes((CostumesHeader, Option<usize>)),
@@ -85,7 +132,9 @@ impl Header {
     fn kind(&self) -> &'static str {
         match self {
             Header::Set(_) => "set",
+            Header::Sprite(_) => "sprite",
             Header::Vars(_) => "vars",
+            Header::Consts(_) => "const",
             Header::Lists(_) => "lists",
             Header::Broadcasts(_) => "broadcasts",
             Header::Costumes(_) => "costumes",
@@ -97,7 +146,9 @@ impl Header {
 fn any_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Header, ParseErr<'src>> {
     choice((
         set_header().map(Header::Set),
+        sprite_header().map(Header::Sprite),
         vars_header().map(Header::Vars),
+        consts_header().map(Header::Consts),
         lists_header().map(Header::Lists),
         brodcasts_header().map(Header::Broadcasts),
         costumes_header().map(Header::Costumes),
@@ -105,78 +156,289 @@ fn any_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Header, Parse
     ))
 }
 
-fn ident<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, (Ident, Span), ParseErr<'src>> {
-    select! {
-        Token::Ident(ident) => Ident::new(ident.to_string())
-    }
-    .labelled("identifier")
-    .map_with(|var_name, e| (var_name, e.span()))
+/// A value in the `set` or `sprite` header
+#[derive(Debug, Clone)]
+enum SettingValue {
+    Number(Number),
+    Bool(bool),
+    String(String),
 }
 
-fn value<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Value, ParseErr<'src>> {
-    select! {
-        Token::Number(num) => Value::Number(num),
-        Token::String(string) => Value::String(string.to_string()),
-    }
+/// Stores a value of a header's setting, on failure it returns what the setting expects
+type StoreSetting<S> = fn(&mut S, &str, SettingValue) -> Result<(), String>;
+
+/// A header of `name = value;` declarations like `set` and `sprite`
+fn settings_header<'src, I: ParseInput<'src>, S>(
+    keyword: impl Parser<'src, I, (), ParseErr<'src>>,
+    names: &'static [&'static str],
+    init: fn(Span) -> S,
+    store: StoreSetting<S>,
+) -> impl Parser<'src, I, S, ParseErr<'src>> {
+    let number = just(Token::Minus)
+        .or_not()
+        .then(select! { Token::Number(num) => num })
+        .map(|(minus, num)| if minus.is_some() { negate(num) } else { num });
+
+    let setting_value = choice((
+        number.map(SettingValue::Number),
+        select! {
+            Token::Ident("true") => SettingValue::Bool(true),
+            Token::Ident("false") => SettingValue::Bool(false),
+            Token::String(string) => SettingValue::String(string.to_string()),
+        },
+    ))
     .labelled("value")
+    .map_with(|val, e| (val, e.span()));
+
+    let decl = ident()
+        .then(just(Token::Equals).ignore_then(setting_value))
+        .then_ignore(just(Token::Semicolon));
+
+    keyword.ignore_then(
+        decl.repeated()
+            .at_least(1)
+            .collect::<Vec<_>>()
+            .delimited_by(just(Token::CurlyOpen), just(Token::CurlyClose))
+            .validate(move |decls, e, emitter| {
+                let mut settings = init(e.span());
+                let mut seen = Vec::new();
+                for ((id, span), (val, val_span)) in decls {
+                    if !names.contains(&id.as_str()) {
+                        emitter.emit(Rich::custom(span, format!("'{id}' is no valid setting")));
+                        continue;
+                    }
+                    if seen.contains(&id) {
+                        emitter.emit(Rich::custom(span, format!("Setting '{id}' is set twice")));
+                        continue;
+                    }
+
+                    if let Err(expected) = store(&mut settings, id.as_str(), val) {
+                        emitter.emit(Rich::custom(
+                            val_span,
+                            format!("'{id}' has to be {expected}"),
+                        ));
+                    }
+                    seen.push(id);
+                }
+                settings
+            }),
+    )
 }
 
-// TODO: better validation (on values)
-fn set_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, SetHeader, ParseErr<'src>> {
-    let valid_setting = ident().validate(|(id, span), _, emitter| {
-        if !matches!(
-            id.as_str(),
-            "tempo" | "volume" | "videoTransparency" | "videoState"
-        ) {
-            emitter.emit(Rich::custom(span, format!("'{id}' is no valid setting")));
+fn set_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, StageSettings, ParseErr<'src>> {
+    settings_header(
+        just(Token::Set).ignored(),
+        &[
+            "tempo",
+            "volume",
+            "videoTransparency",
+            "videoState",
+            "textToSpeechLanguage",
+        ],
+        |_| StageSettings::default(),
+        stage_setting,
+    )
+}
+
+fn stage_setting(settings: &mut StageSettings, id: &str, val: SettingValue) -> Result<(), String> {
+    match id {
+        "tempo" => {
+            settings.tempo = Some(
+                integer(&val, 20..=500)
+                    .ok_or("a whole number of beats per minute from 20 to 500")?,
+            )
+        }
+        "volume" => {
+            settings.volume = Some(integer(&val, 0..=100).ok_or("a percentage from 0 to 100")?)
+        }
+        "videoTransparency" => {
+            settings.video_transparency =
+                Some(integer(&val, 0..=100).ok_or("a percentage from 0 to 100")?)
         }
-        (id, span)
-    });
+        "videoState" => {
+            settings.video_state = Some(match val {
+                SettingValue::String(state) if state == "on" => VideoState::On,
+                SettingValue::String(state) if state == "off" => VideoState::Off,
+                SettingValue::String(state) if state == "on-flipped" => VideoState::OnFlipped,
+                _ => return Err(String::from(r#""on", "off" or "on-flipped""#)),
+            })
+        }
+        "textToSpeechLanguage" => {
+            let language = match val {
+                SettingValue::String(code) => code.parse().ok(),
+                _ => None,
+            };
+            settings.text_to_speech_language = Some(language.ok_or_else(|| {
+                let codes: Vec<_> = Language::ALL.iter().map(|l| l.code()).collect();
+                format!("one of the language codes {}", codes.join(", "))
+            })?);
+        }
+        _ => unreachable!("not in the names of the `set` header"),
+    }
+    Ok(())
+}
+
+fn sprite_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, SpriteSettings, ParseErr<'src>>
+{
+    settings_header(
+        select! { Token::Ident("sprite") => () },
+        &[
+            "x",
+            "y",
+            "size",
+            "direction",
+            "visible",
+            "draggable",
+            "rotationStyle",
+        ],
+        |span| SpriteSettings {
+            x: None,
+            y: None,
+            size: None,
+            direction: None,
+            visible: None,
+            draggable: None,
+            rotation_style: None,
+            span,
+        },
+        sprite_setting,
+    )
+}
 
-    let decl = valid_setting
-        .then(just(Token::Equals).ignore_then(value()))
+fn sprite_setting(
+    settings: &mut SpriteSettings,
+    id: &str,
+    val: SettingValue,
+) -> Result<(), String> {
+    let boolean = |val| match val {
+        SettingValue::Bool(val) => Ok(val),
+        _ => Err("true or false"),
+    };
+
+    match id {
+        "x" | "y" => {
+            let SettingValue::Number(num) = val else {
+                return Err(String::from("a number"));
+            };
+            if id == "x" {
+                settings.x = Some(num);
+            } else {
+                settings.y = Some(num);
+            }
+        }
+        "size" => {
+            settings.size = Some(integer(&val, 0..=Percentage::MAX.into()).ok_or("a percentage")?)
+        }
+        "direction" => {
+            let direction: i32 =
+                integer(&val, -179..=180).ok_or("a whole number of degrees from -179 to 180")?;
+            settings.direction = Some(direction.rem_euclid(360) as Angle);
+        }
+        "visible" => settings.visible = Some(boolean(val)?),
+        "draggable" => settings.draggable = Some(boolean(val)?),
+        "rotationStyle" => {
+            settings.rotation_style = Some(match val {
+                SettingValue::String(style) if style == "all around" => RotationStyle::AllAround,
+                SettingValue::String(style) if style == "left-right" => RotationStyle::LeftRight,
+                SettingValue::String(style) if style == "don't rotate" => RotationStyle::DontRotate,
+                _ => {
+                    return Err(String::from(
+                        r#""all around", "left-right" or "don't rotate""#,
+                    ));
+                }
+            })
+        }
+        _ => unreachable!("not in the names of the `sprite` header"),
+    }
+    Ok(())
+}
+
+/// The value as an integer within `range`
+fn integer<T: TryFrom<i32>>(val: &SettingValue, range: RangeInclusive<i32>) -> Option<T> {
+    match val {
+        SettingValue::Number(Number::Integer(num)) if range.contains(num) => T::try_from(*num).ok(),
+        _ => None,
+    }
+}
+
+fn vars_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, VarsHeader, ParseErr<'src>> {
+    let decl = just(Token::Cloud)
+        .or_not()
+        .map(|cloud| cloud.is_some())
+        .then(ident())
+        .then(
+            just(Token::Equals)
+                .ignore_then(value().map_with(|val, e| (val, e.span())))
+                .or_not(),
+        )
         .then_ignore(just(Token::Semicolon));
 
-    just(Token::Set).ignore_then(
+    just(Token::Vars).ignore_then(
         decl.repeated()
             .at_least(1)
             .collect::<Vec<_>>()
             .validate(|decls, _, emitter| {
-                let mut settings = HashMap::new();
-                for ((id, span), val) in decls {
-                    if settings.insert(id.clone(), val).is_some() {
+                let mut vars = HashMap::new();
+                let mut clouds = 0;
+                for ((cloud, (id, span)), val) in decls {
+                    if cloud {
+                        clouds += 1;
+                        if clouds > MAX_CLOUD_VARIABLES {
+                            emitter.emit(Rich::custom(
+                                span,
+                                format!(
+                                    "A project can't have more than {MAX_CLOUD_VARIABLES} cloud variables"
+                                ),
+                            ));
+                        }
+
+                        if let Some((Value::String(_), val_span)) = &val {
+                            emitter.emit(Rich::custom(
+                                *val_span,
+                                format!("Cloud variable '{id}' can only hold numbers"),
+                            ));
+                        }
+                    }
+
+                    let decl = VarDecl {
+                        value: val.map(|(val, _)| val),
+                        cloud,
+                        span,
+                    };
+                    if vars.insert(id.clone(), decl).is_some() {
                         emitter.emit(Rich::custom(
                             span,
                             format!("Variable '{}' already exists", id),
                         ));
                     }
                 }
-                settings
+                vars
             })
             .delimited_by(just(Token::CurlyOpen), just(Token::CurlyClose)),
     )
 }
 
-fn vars_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, VarsHeader, ParseErr<'src>> {
+fn consts_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, ConstsHeader, ParseErr<'src>>
+{
     let decl = ident()
-        .then(just(Token::Equals).ignore_then(value()).or_not())
+        .then(just(Token::Equals).ignore_then(expr()))
         .then_ignore(just(Token::Semicolon));
 
-    just(Token::Vars).ignore_then(
+    just(Token::Const).ignore_then(
         decl.repeated()
             .at_least(1)
             .collect::<Vec<_>>()
             .validate(|decls, _, emitter| {
-                let mut vars = HashMap::new();
-                for ((id, span), val) in decls {
-                    if vars.insert(id.clone(), val).is_some() {
+                let mut consts = HashMap::new();
+                for ((id, span), expr) in decls {
+                    if consts.insert(id.clone(), (expr, span)).is_some() {
                         emitter.emit(Rich::custom(
                             span,
-                            format!("Variable '{}' already exists", id),
+                            format!("Constant '{}' already exists", id),
                         ));
                     }
                 }
-                vars
+                consts
             })
             .delimited_by(just(Token::CurlyOpen), just(Token::CurlyClose)),
     )
@@ -205,7 +467,7 @@ fn lists_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, ListsHeader
             .validate(|decls, _, emitter| {
                 let mut lists = HashMap::new();
                 for ((id, span), val) in decls {
-                    if lists.insert(id.clone(), val).is_some() {
+                    if lists.insert(id.clone(), (val, span)).is_some() {
                         emitter.emit(Rich::custom(span, format!("List '{}' already exists", id)));
                     }
                 }
@@ -224,9 +486,9 @@ fn brodcasts_header<'src, I: ParseInput<'src>>()
             .at_least(1)
             .collect::<Vec<_>>()
             .validate(|decls, _, emitter| {
-                let mut broadcasts = HashSet::new();
+                let mut broadcasts = HashMap::new();
                 for (id, span) in decls {
-                    if !broadcasts.insert(id.clone()) {
+                    if broadcasts.insert(id.clone(), span).is_some() {
                         emitter.emit(Rich::custom(
                             span,
                             format!("Broadcast '{id}' declared twice"),
@@ -276,7 +538,10 @@ fn costumes_header<'src, I: ParseInput<'src>>()
                         current_costume = Some(i);
                     }
 
-                    if costumes.insert(id.clone(), (file_type, path)).is_some() {
+                    if costumes
+                        .insert(id.clone(), (file_type, path, span))
+                        .is_some()
+                    {
                         emitter.emit(Rich::custom(
                             span,
                             format!("Broadcast '{id}' declared twice"),
@@ -312,7 +577,7 @@ fn sounds_header<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, SoundsHead
             .validate(|decls, _, emitter| {
                 let mut sounds = HashMap::new();
                 for (((id, span), file_type), path) in decls {
-                    if sounds.insert(id.clone(), (file_type, path)).is_some() {
+                    if sounds.insert(id.clone(), (file_type, path, span)).is_some() {
                         emitter.emit(Rich::custom(
                             span,
                             format!("Broadcast '{id}' declared twice"),
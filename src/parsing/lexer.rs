@@ -27,8 +27,13 @@ pub enum Token<'src> {
     Comma,
     Colon,
     DoubleColon,
+    DoubleDot,
+    Dot,
     Semicolon,
     Equals,
+    PlusEquals,
+    MinusEquals,
+    FatArrow,
 
     Plus,
     Minus,
@@ -43,12 +48,21 @@ pub enum Token<'src> {
     Broadcasts,
     Costumes,
     Sounds,
+    Cloud,
+    Const,
 
+    Import,
     Def,
     When,
     If,
     Else,
+    Match,
     Repeat,
+    Until,
+    While,
+    Forever,
+    For,
+    In,
 
     Img(ImgType),
     Audio(AudioType),
@@ -71,8 +85,13 @@ impl fmt::Display for Token<'_> {
             Token::Comma => write!(f, ","),
             Token::Colon => write!(f, ":"),
             Token::DoubleColon => write!(f, "::"),
+            Token::DoubleDot => write!(f, ".."),
+            Token::Dot => write!(f, "."),
             Token::Semicolon => write!(f, ";"),
             Token::Equals => write!(f, "="),
+            Token::PlusEquals => write!(f, "+="),
+            Token::MinusEquals => write!(f, "-="),
+            Token::FatArrow => write!(f, "=>"),
             Token::Plus => write!(f, "+"),
             Token::Minus => write!(f, "-"),
             Token::Asterisk => write!(f, "*"),
@@ -85,17 +104,27 @@ impl fmt::Display for Token<'_> {
             Token::Broadcasts => write!(f, "broadcasts"),
             Token::Costumes => write!(f, "costumes"),
             Token::Sounds => write!(f, "sounds"),
+            Token::Cloud => write!(f, "cloud"),
+            Token::Const => write!(f, "const"),
+            Token::Import => write!(f, "import"),
             Token::Def => write!(f, "def"),
             Token::When => write!(f, "when"),
             Token::If => write!(f, "if"),
             Token::Else => write!(f, "else"),
+            Token::Match => write!(f, "match"),
             Token::Repeat => write!(f, "repeat"),
+            Token::Until => write!(f, "until"),
+            Token::While => write!(f, "while"),
+            Token::Forever => write!(f, "forever"),
+            Token::For => write!(f, "for"),
+            Token::In => write!(f, "in"),
             Token::Img(x) => write!(f, "{x}"),
             Token::Audio(x) => write!(f, "{x}"),
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn lexer<'src, F>() -> impl Parser<
     'src,
     MappedSpan<Span, &'src str, F>,
@@ -150,25 +179,32 @@ where
     // A parser for symbols
     let symbol = just("::")
         .to(Token::DoubleColon)
-        .or(one_of("()[]<>{}=,:;+-*/").map(|symbol: char| match symbol {
-            '(' => Token::ParenOpen,
-            ')' => Token::ParenClose,
-            '[' => Token::BracketOpen,
-            ']' => Token::BracketClose,
-            '<' => Token::AngleOpen,
-            '>' => Token::AngleClose,
-            '{' => Token::CurlyOpen,
-            '}' => Token::CurlyClose,
-            '=' => Token::Equals,
-            ',' => Token::Comma,
-            ':' => Token::Colon,
-            ';' => Token::Semicolon,
-            '+' => Token::Plus,
-            '-' => Token::Minus,
-            '*' => Token::Asterisk,
-            '/' => Token::Slash,
-            _ => unreachable!("because of one_of()"),
-        }));
+        .or(just("..").to(Token::DoubleDot))
+        .or(just("=>").to(Token::FatArrow))
+        .or(just("+=").to(Token::PlusEquals))
+        .or(just("-=").to(Token::MinusEquals))
+        .or(
+            one_of("()[]<>{}=,.:;+-*/").map(|symbol: char| match symbol {
+                '(' => Token::ParenOpen,
+                ')' => Token::ParenClose,
+                '[' => Token::BracketOpen,
+                ']' => Token::BracketClose,
+                '<' => Token::AngleOpen,
+                '>' => Token::AngleClose,
+                '{' => Token::CurlyOpen,
+                '}' => Token::CurlyClose,
+                '=' => Token::Equals,
+                ',' => Token::Comma,
+                '.' => Token::Dot,
+                ':' => Token::Colon,
+                ';' => Token::Semicolon,
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                '*' => Token::Asterisk,
+                '/' => Token::Slash,
+                _ => unreachable!("because of one_of()"),
+            }),
+        );
 
     // A parser for identifiers and keywords
     let ident = text::ascii::ident().map(|ident: &str| match ident {
@@ -178,12 +214,21 @@ where
         "broadcasts" => Token::Broadcasts,
         "costumes" => Token::Costumes,
         "sounds" => Token::Sounds,
+        "cloud" => Token::Cloud,
+        "const" => Token::Const,
 
+        "import" => Token::Import,
         "def" => Token::Def,
         "when" => Token::When,
         "if" => Token::If,
         "else" => Token::Else,
+        "match" => Token::Match,
         "repeat" => Token::Repeat,
+        "until" => Token::Until,
+        "while" => Token::While,
+        "forever" => Token::Forever,
+        "for" => Token::For,
+        "in" => Token::In,
 
         "greater" => Token::Greater,
         "less" => Token::Less,
@@ -254,4 +299,69 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn ranges() {
+        let (tokens, errors) = lexer()
+            .parse("for i in 1..10.5 {}".map_span(Into::into))
+            .into_output_errors();
+
+        assert!(errors.is_empty());
+
+        let tokens: Vec<Token> = tokens
+            .unwrap()
+            .into_iter()
+            .map(|(tok, _span)| tok)
+            .collect();
+
+        use Token::*;
+        assert_eq!(
+            tokens,
+            vec![
+                For,
+                Ident("i"),
+                In,
+                Number(scratch_sb3::Number::Integer(1)),
+                DoubleDot,
+                Number(scratch_sb3::Number::Float(10.5)),
+                CurlyOpen,
+                CurlyClose,
+            ]
+        );
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let (tokens, errors) = lexer()
+            .parse("x += 1; x -= -2; x = 3;".map_span(Into::into))
+            .into_output_errors();
+
+        assert!(errors.is_empty());
+
+        let tokens: Vec<Token> = tokens
+            .unwrap()
+            .into_iter()
+            .map(|(tok, _span)| tok)
+            .collect();
+
+        use Token::*;
+        assert_eq!(
+            tokens,
+            vec![
+                Ident("x"),
+                PlusEquals,
+                Number(scratch_sb3::Number::Integer(1)),
+                Semicolon,
+                Ident("x"),
+                MinusEquals,
+                Minus,
+                Number(scratch_sb3::Number::Integer(2)),
+                Semicolon,
+                Ident("x"),
+                Equals,
+                Number(scratch_sb3::Number::Integer(3)),
+                Semicolon,
+            ]
+        );
+    }
 }
@@ -1,6 +1,7 @@
 mod error;
 mod headers;
 mod lexer;
+mod scripts;
 mod span;
 
 use std::fmt;
@@ -8,10 +9,16 @@ use std::fmt;
 use chumsky::input::ValueInput;
 use chumsky::prelude::*;
 
+use scratch_sb3::Value;
+
 pub use error::ParsingError;
 use error::build_error;
-pub use headers::Headers;
+pub use headers::{Headers, SpriteSettings, StageSettings, VarDecl};
 use lexer::Token;
+pub use scripts::{
+    Arg, BinOp, BlockCall, Expr, ExprKind, Field, FieldKind, MatchArm, Param, ParamKind, Script,
+    ScriptKind, Segment, Signature, SignaturePart, Stmt, StmtKind,
+};
 pub use span::Span;
 
 type ParseErr<'src> = chumsky::extra::Err<Rich<'src, lexer::Token<'src>, Span>>;
@@ -38,7 +45,68 @@ impl fmt::Display for Ident {
     }
 }
 
-pub fn parse(source: &str) -> Result<Headers, Vec<ParsingError>> {
+/// A parsed `.scr` file: its imports, headers and scripts
+#[derive(Debug)]
+pub struct ScrFile {
+    pub imports: Vec<Import>,
+    pub headers: Headers,
+    pub scripts: Vec<Script>,
+}
+
+impl ScrFile {
+    fn parser<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, ScrFile, ParseErr<'src>> {
+        Import::parser()
+            .repeated()
+            .collect()
+            .then(Headers::parser())
+            .then(Script::parser().repeated().collect())
+            .map(|((imports, headers), scripts)| ScrFile {
+                imports,
+                headers,
+                scripts,
+            })
+    }
+}
+
+/// An `import "path/to/file.scr";` statement
+///
+/// The path is relative to the importing file.
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub path: String,
+    pub span: Span,
+}
+
+impl Import {
+    fn parser<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Import, ParseErr<'src>> {
+        just(Token::Import)
+            .ignore_then(select! { Token::String(path) => path.to_string() }.labelled("path"))
+            .then_ignore(just(Token::Semicolon))
+            .map_with(|path, e| Import {
+                path,
+                span: e.span(),
+            })
+    }
+}
+
+fn ident<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, (Ident, Span), ParseErr<'src>> + Clone
+{
+    select! {
+        Token::Ident(ident) => Ident::new(ident.to_string())
+    }
+    .labelled("identifier")
+    .map_with(|var_name, e| (var_name, e.span()))
+}
+
+fn value<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Value, ParseErr<'src>> + Clone {
+    select! {
+        Token::Number(num) => Value::Number(num),
+        Token::String(string) => Value::String(string.to_string()),
+    }
+    .labelled("value")
+}
+
+pub fn parse(source: &str) -> Result<ScrFile, Vec<ParsingError>> {
     let (tokens, lex_errs) = lexer::lexer()
         .parse(source.map_span(Into::into))
         .into_output_errors();
@@ -47,12 +115,12 @@ pub fn parse(source: &str) -> Result<Headers, Vec<ParsingError>> {
     let end_of_input: Span = Span::marker(char_count);
 
     let parse_errs = if let Some(tokens) = &tokens {
-        let (headers, parse_errs) = Headers::parser()
+        let (file, parse_errs) = ScrFile::parser()
             .parse(tokens.as_slice().map(end_of_input, |(t, s)| (t, s)))
             .into_output_errors();
 
-        if let Some(headers) = headers.filter(|_| lex_errs.len() + parse_errs.len() == 0) {
-            return Ok(headers);
+        if let Some(file) = file.filter(|_| lex_errs.len() + parse_errs.len() == 0) {
+            return Ok(file);
         }
 
         parse_errs
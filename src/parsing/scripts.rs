@@ -0,0 +1,713 @@
+use chumsky::input::MapExtra;
+use chumsky::prelude::*;
+
+use scratch_sb3::{Number, Value};
+
+use super::lexer::Token;
+use super::{Ident, ParseErr, ParseInput, Span, ident};
+
+/// A top-level script: either an event script (`when ...`) or a custom block definition (`def ...`)
+#[derive(Debug, Clone)]
+pub struct Script {
+    pub kind: ScriptKind,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ScriptKind {
+    When(BlockCall),
+    Def(Signature),
+}
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
+    Call(BlockCall),
+    If {
+        cond: Expr,
+        then: Vec<Stmt>,
+        otherwise: Option<Vec<Stmt>>,
+    },
+    Repeat {
+        times: Expr,
+        body: Vec<Stmt>,
+    },
+    RepeatUntil {
+        cond: Expr,
+        body: Vec<Stmt>,
+    },
+    /// Sugar for repeating until the condition is false
+    While {
+        cond: Expr,
+        body: Vec<Stmt>,
+    },
+    Forever(Vec<Stmt>),
+    /// Run the first arm with a pattern equal to `value`, comparing like `operator_equals`
+    ///
+    /// `value` is evaluated only once, before any pattern.
+    Match {
+        value: Expr,
+        arms: Vec<MatchArm>,
+        default: Option<Vec<Stmt>>,
+    },
+    /// Count the variable `var` from `from` up to and including `to`
+    ///
+    /// Unlike in Rust, `from..to` is inclusive, so `1..10` runs ten times. It doesn't run at all if `to` is less than `from`.
+    For {
+        var: Ident,
+        var_span: Span,
+        from: Expr,
+        to: Expr,
+        body: Vec<Stmt>,
+    },
+}
+
+/// `pattern, ... => { ... }` in a `match` statement
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub patterns: Vec<Expr>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Literal(Value),
+    Ident(Ident),
+    Call(BlockCall),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Greater,
+    Less,
+    Equals,
+}
+
+/// A block invocation like `sound::play_sound_[sound1]_until_done`
+///
+/// Blocks without a namespace are calls to custom blocks.
+#[derive(Debug, Clone)]
+pub struct BlockCall {
+    pub namespace: Option<Ident>,
+    pub segments: Vec<Segment>,
+    pub span: Span,
+}
+
+impl BlockCall {
+    /// The shape of the block with its arguments left out, e.g. `play_sound_[]_until_done`
+    pub fn pattern(&self) -> String {
+        let mut pattern = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Label(label) => pattern.push_str(label),
+                Segment::Arg(Arg::Reporter(_)) => pattern.push_str("()"),
+                Segment::Arg(Arg::Boolean(_)) => pattern.push_str("<>"),
+                Segment::Arg(Arg::Field(_)) => pattern.push_str("[]"),
+            }
+        }
+        pattern
+    }
+
+    pub fn args(&self) -> impl Iterator<Item = &Arg> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Label(_) => None,
+            Segment::Arg(arg) => Some(arg),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Segment {
+    Label(String),
+    Arg(Arg),
+}
+
+#[derive(Debug, Clone)]
+pub enum Arg {
+    /// A round slot `(...)`
+    Reporter(Expr),
+    /// A hexagonal slot `<...>`
+    Boolean(Expr),
+    /// A dropdown field `[...]`
+    Field(Field),
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub kind: FieldKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum FieldKind {
+    Ident(Ident),
+    String(String),
+}
+
+/// The head of a custom block definition like `dance_(times)_if_<cond>`
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub parts: Vec<SignaturePart>,
+    pub span: Span,
+}
+
+impl Signature {
+    /// The shape of the custom block, matching [`BlockCall::pattern`] of its calls
+    pub fn pattern(&self) -> String {
+        let mut pattern = String::new();
+        for part in &self.parts {
+            match part {
+                SignaturePart::Label(label) => pattern.push_str(label),
+                SignaturePart::Param(param) => match param.kind {
+                    ParamKind::Reporter => pattern.push_str("()"),
+                    ParamKind::Boolean => pattern.push_str("<>"),
+                },
+            }
+        }
+        pattern
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = &Param> {
+        self.parts.iter().filter_map(|part| match part {
+            SignaturePart::Label(_) => None,
+            SignaturePart::Param(param) => Some(param),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SignaturePart {
+    Label(String),
+    Param(Param),
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: Ident,
+    pub kind: ParamKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Reporter,
+    Boolean,
+}
+
+impl Script {
+    pub fn parser<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Script, ParseErr<'src>> {
+        let body = stmt()
+            .repeated()
+            .collect::<Vec<_>>()
+            .delimited_by(just(Token::CurlyOpen), just(Token::CurlyClose));
+
+        let when = just(Token::When)
+            .ignore_then(block_call(expr(), true))
+            .map(ScriptKind::When);
+
+        let def = just(Token::Def)
+            .ignore_then(signature())
+            .map(ScriptKind::Def);
+
+        when.or(def).then(body).map_with(|(kind, body), e| Script {
+            kind,
+            body,
+            span: e.span(),
+        })
+    }
+}
+
+fn stmt<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Stmt, ParseErr<'src>> + Clone {
+    recursive(|stmt| {
+        let body = stmt
+            .clone()
+            .repeated()
+            .collect::<Vec<_>>()
+            .delimited_by(just(Token::CurlyOpen), just(Token::CurlyClose));
+
+        let expr = expr();
+
+        let call = block_call(expr.clone(), false)
+            .then_ignore(just(Token::Semicolon))
+            .map(StmtKind::Call);
+
+        let list_method = list_method(expr.clone())
+            .then_ignore(just(Token::Semicolon))
+            .map(StmtKind::Call);
+
+        // `x = 1;`, `x += 1;` and `x -= 1;` are sugar for `data::set_[x]_to_(1);` and friends
+        let operator = select! {
+            Token::Equals => ("set_", "_to_", false),
+            Token::PlusEquals => ("change_", "_by_", false),
+            Token::MinusEquals => ("change_", "_by_", true),
+        };
+        let assignment = ident_field()
+            .then(operator)
+            .then(expr.clone())
+            .then_ignore(just(Token::Semicolon))
+            .map_with(|((var, (set, to, negative)), value), e| {
+                let value = if negative { negated(value) } else { value };
+                StmtKind::Call(data_call(
+                    vec![
+                        Segment::Label(set.to_string()),
+                        Segment::Arg(Arg::Field(var)),
+                        Segment::Label(to.to_string()),
+                        Segment::Arg(Arg::Reporter(value)),
+                    ],
+                    e.span(),
+                ))
+            });
+
+        // `show` and `hide` aren't keywords, as blocks like `looks::show` use them as labels
+        let visibility = select! {
+            Token::Ident("show") => "show_variable_",
+            Token::Ident("hide") => "hide_variable_",
+        }
+        .then(ident_field())
+        .then_ignore(just(Token::Semicolon))
+        .map_with(|(label, var), e| {
+            StmtKind::Call(data_call(
+                vec![
+                    Segment::Label(label.to_string()),
+                    Segment::Arg(Arg::Field(var)),
+                ],
+                e.span(),
+            ))
+        });
+
+        let condition = expr
+            .clone()
+            .delimited_by(just(Token::AngleOpen), just(Token::AngleClose));
+
+        let if_else = just(Token::If)
+            .ignore_then(condition.clone())
+            .then(body.clone())
+            .then(
+                just(Token::Else)
+                    .ignore_then(choice((
+                        body.clone(),
+                        // `else if` is an `if` nested in the else branch
+                        just(Token::If)
+                            .rewind()
+                            .ignore_then(stmt.clone())
+                            .map(|stmt| vec![stmt]),
+                    )))
+                    .or_not(),
+            )
+            .map(|((cond, then), otherwise)| StmtKind::If {
+                cond,
+                then,
+                otherwise,
+            });
+
+        let repeat = just(Token::Repeat)
+            .ignore_then(
+                expr.clone()
+                    .delimited_by(just(Token::ParenOpen), just(Token::ParenClose)),
+            )
+            .then(body.clone())
+            .map(|(times, body)| StmtKind::Repeat { times, body });
+
+        let repeat_until = just(Token::Repeat)
+            .ignore_then(just(Token::Until))
+            .ignore_then(condition.clone())
+            .then(body.clone())
+            .map(|(cond, body)| StmtKind::RepeatUntil { cond, body });
+
+        let while_loop = just(Token::While)
+            .ignore_then(condition)
+            .then(body.clone())
+            .map(|(cond, body)| StmtKind::While { cond, body });
+
+        let wildcard = just(Token::Ident("_"));
+
+        // `_` would be taken for an identifier otherwise
+        let arm = wildcard
+            .clone()
+            .not()
+            .ignore_then(
+                expr.clone()
+                    .separated_by(just(Token::Comma))
+                    .at_least(1)
+                    .collect::<Vec<_>>(),
+            )
+            .then_ignore(just(Token::FatArrow))
+            .then(body.clone())
+            .map_with(|(patterns, body), e| MatchArm {
+                patterns,
+                body,
+                span: e.span(),
+            });
+
+        let default = wildcard
+            .ignore_then(just(Token::FatArrow))
+            .ignore_then(body.clone());
+
+        let match_stmt = just(Token::Match)
+            .ignore_then(
+                expr.clone()
+                    .delimited_by(just(Token::ParenOpen), just(Token::ParenClose)),
+            )
+            .then(
+                arm.repeated()
+                    .collect::<Vec<_>>()
+                    .then(default.or_not())
+                    .delimited_by(just(Token::CurlyOpen), just(Token::CurlyClose)),
+            )
+            .map(|(value, (arms, default))| StmtKind::Match {
+                value,
+                arms,
+                default,
+            });
+
+        let forever = just(Token::Forever)
+            .ignore_then(body.clone())
+            .map(StmtKind::Forever);
+
+        let for_loop = just(Token::For)
+            .ignore_then(ident())
+            .then_ignore(just(Token::In))
+            .then(expr.clone())
+            .then_ignore(just(Token::DoubleDot))
+            .then(expr)
+            .then(body)
+            .map(|((((var, var_span), from), to), body)| StmtKind::For {
+                var,
+                var_span,
+                from,
+                to,
+                body,
+            });
+
+        choice((
+            if_else,
+            repeat,
+            repeat_until,
+            while_loop,
+            forever,
+            match_stmt,
+            for_loop,
+            list_method,
+            assignment,
+            visibility,
+            call,
+        ))
+        .map_with(|kind, e| Stmt {
+            kind,
+            span: e.span(),
+        })
+    })
+    .boxed()
+}
+
+pub(super) fn expr<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Expr, ParseErr<'src>> + Clone
+{
+    recursive(|expr| {
+        let number = select! { Token::Number(num) => num };
+        let literal = choice((
+            number.map(Value::Number),
+            just(Token::Minus)
+                .ignore_then(number)
+                .map(|num| Value::Number(negate(num))),
+            select! { Token::String(string) => Value::String(string.to_string()) },
+        ))
+        .labelled("value")
+        .map(ExprKind::Literal);
+
+        // `items[i]` is sugar for `data::item_(i)_of_[items]`
+        let list_item = ident_field()
+            .then(
+                expr.clone()
+                    .delimited_by(just(Token::BracketOpen), just(Token::BracketClose)),
+            )
+            .map_with(|(list, index), e| {
+                data_call(
+                    vec![
+                        Segment::Label("item_".to_string()),
+                        Segment::Arg(Arg::Reporter(index)),
+                        Segment::Label("_of_".to_string()),
+                        Segment::Arg(Arg::Field(list)),
+                    ],
+                    e.span(),
+                )
+            });
+
+        let atom = choice((
+            literal,
+            block_call(expr.clone(), true).map(ExprKind::Call),
+            list_method(expr.clone()).map(ExprKind::Call),
+            list_item.map(ExprKind::Call),
+            ident().map(|(id, _)| ExprKind::Ident(id)),
+        ))
+        .map_with(|kind, e| Expr {
+            kind,
+            span: e.span(),
+        })
+        .or(expr
+            .clone()
+            .delimited_by(just(Token::ParenOpen), just(Token::ParenClose)))
+        .or(expr
+            .clone()
+            .delimited_by(just(Token::AngleOpen), just(Token::AngleClose)));
+
+        let product = atom.clone().foldl_with(
+            choice((
+                just(Token::Asterisk).to(BinOp::Mul),
+                just(Token::Slash).to(BinOp::Div),
+            ))
+            .then(atom)
+            .repeated(),
+            binary,
+        );
+
+        let sum = product.clone().foldl_with(
+            choice((
+                just(Token::Plus).to(BinOp::Add),
+                just(Token::Minus).to(BinOp::Sub),
+            ))
+            .then(product)
+            .repeated(),
+            binary,
+        );
+
+        sum.clone().foldl_with(
+            choice((
+                just(Token::Greater).to(BinOp::Greater),
+                just(Token::Less).to(BinOp::Less),
+                just(Token::Equals).to(BinOp::Equals),
+            ))
+            .then(sum)
+            .repeated(),
+            binary,
+        )
+    })
+    .boxed()
+}
+
+fn block_call<'src, I: ParseInput<'src>>(
+    expr: impl Parser<'src, I, Expr, ParseErr<'src>> + Clone + 'src,
+    namespaced: bool,
+) -> impl Parser<'src, I, BlockCall, ParseErr<'src>> + Clone {
+    let label = select! { Token::Ident(label) => label.to_string() }.labelled("label");
+
+    let field = select! {
+        Token::Ident(id) => FieldKind::Ident(Ident::new(id.to_string())),
+        Token::String(string) => FieldKind::String(string.to_string()),
+    }
+    .map_with(|kind, e| Field {
+        kind,
+        span: e.span(),
+    })
+    .delimited_by(just(Token::BracketOpen), just(Token::BracketClose));
+
+    let arg = choice((
+        expr.clone()
+            .delimited_by(just(Token::ParenOpen), just(Token::ParenClose))
+            .map(Arg::Reporter),
+        expr.delimited_by(just(Token::AngleOpen), just(Token::AngleClose))
+            .map(Arg::Boolean),
+        field.map(Arg::Field),
+    ));
+
+    let segment = label.map(Segment::Label).or(arg.map(Segment::Arg));
+
+    // The namespace already marks the start of a builtin block, so it may begin with an argument
+    let builtin = ident()
+        .then_ignore(just(Token::DoubleColon))
+        .map(|(id, _)| Some(id))
+        .then(segment.clone().repeated().at_least(1).collect::<Vec<_>>());
+
+    let custom = label
+        .map(Segment::Label)
+        .then(segment.repeated().collect::<Vec<_>>())
+        .map(|(first, mut rest)| {
+            rest.insert(0, first);
+            (None, rest)
+        });
+
+    let call = if namespaced {
+        builtin.boxed()
+    } else {
+        builtin.or(custom).boxed()
+    };
+
+    call.map_with(|(namespace, segments), e| BlockCall {
+        namespace,
+        segments,
+        span: e.span(),
+    })
+}
+
+/// An identifier used like a dropdown field, e.g. the list of `items.push(x)`
+fn ident_field<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Field, ParseErr<'src>> + Clone {
+    ident().map(|(name, span)| Field {
+        kind: FieldKind::Ident(name),
+        span,
+    })
+}
+
+/// A method call on a list like `items.push(x)`, which is sugar for a block of the `data` namespace
+fn list_method<'src, I: ParseInput<'src>>(
+    expr: impl Parser<'src, I, Expr, ParseErr<'src>> + Clone + 'src,
+) -> impl Parser<'src, I, BlockCall, ParseErr<'src>> + Clone {
+    let args = expr
+        .separated_by(just(Token::Comma))
+        .collect::<Vec<_>>()
+        .delimited_by(just(Token::ParenOpen), just(Token::ParenClose));
+
+    ident_field()
+        .then_ignore(just(Token::Dot))
+        .then(ident())
+        .then(args)
+        .try_map(|((list, (method, method_span)), args), span| {
+            let label = |label: &str| Segment::Label(label.to_string());
+            let reporter = |expr: &Expr| Segment::Arg(Arg::Reporter(expr.clone()));
+            let field = Segment::Arg(Arg::Field(list));
+            let arity = |params: usize| {
+                Rich::custom(
+                    span,
+                    format!(
+                        "'{method}' takes {params} argument(s) but {} were given",
+                        args.len()
+                    ),
+                )
+            };
+
+            let segments = match (method.as_str(), args.as_slice()) {
+                ("push", [item]) => vec![label("add_"), reporter(item), label("_to_"), field],
+                ("insert", [index, item]) => vec![
+                    label("insert_"),
+                    reporter(item),
+                    label("_at_"),
+                    reporter(index),
+                    label("_of_"),
+                    field,
+                ],
+                ("remove", [index]) => vec![label("delete_"), reporter(index), label("_of_"), field],
+                ("clear", []) => vec![label("delete_all_of_"), field],
+                ("len", []) => vec![label("length_of_"), field],
+                ("contains", [item]) => vec![field, label("_contains_"), reporter(item)],
+                ("push" | "remove" | "contains", _) => return Err(arity(1)),
+                ("insert", _) => return Err(arity(2)),
+                ("clear" | "len", _) => return Err(arity(0)),
+                _ => {
+                    return Err(Rich::custom(
+                        method_span,
+                        format!(
+                            "'{method}' is no list method, expected push, insert, remove, clear, len or contains"
+                        ),
+                    ));
+                }
+            };
+            Ok(data_call(segments, span))
+        })
+}
+
+fn data_call(segments: Vec<Segment>, span: Span) -> BlockCall {
+    BlockCall {
+        namespace: Some(Ident::new("data".to_string())),
+        segments,
+        span,
+    }
+}
+
+fn signature<'src, I: ParseInput<'src>>() -> impl Parser<'src, I, Signature, ParseErr<'src>> + Clone
+{
+    block_call(expr(), false).validate(|call, _, emitter| {
+        if call.namespace.is_some() {
+            emitter.emit(Rich::custom(
+                call.span,
+                "Custom blocks cannot have a namespace".to_string(),
+            ));
+        }
+
+        let mut parts = Vec::new();
+        for segment in call.segments {
+            match segment {
+                Segment::Label(label) => parts.push(SignaturePart::Label(label)),
+                Segment::Arg(arg) => {
+                    let (expr, kind) = match arg {
+                        Arg::Reporter(expr) => (expr, ParamKind::Reporter),
+                        Arg::Boolean(expr) => (expr, ParamKind::Boolean),
+                        Arg::Field(field) => {
+                            emitter.emit(Rich::custom(
+                                field.span,
+                                "Custom blocks cannot take fields".to_string(),
+                            ));
+                            continue;
+                        }
+                    };
+
+                    match expr.kind {
+                        ExprKind::Ident(name) => parts.push(SignaturePart::Param(Param {
+                            name,
+                            kind,
+                            span: expr.span,
+                        })),
+                        _ => emitter.emit(Rich::custom(
+                            expr.span,
+                            "Expected a parameter name".to_string(),
+                        )),
+                    }
+                }
+            }
+        }
+
+        Signature {
+            parts,
+            span: call.span,
+        }
+    })
+}
+
+fn binary<'src, I: ParseInput<'src>>(
+    lhs: Expr,
+    (op, rhs): (BinOp, Expr),
+    e: &mut MapExtra<'src, '_, I, ParseErr<'src>>,
+) -> Expr {
+    Expr {
+        kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+        span: e.span(),
+    }
+}
+
+/// `-expr`, which is `0 - expr` unless it's a number literal
+fn negated(expr: Expr) -> Expr {
+    let span = expr.span;
+    let kind = match expr.kind {
+        ExprKind::Literal(Value::Number(num)) => ExprKind::Literal(Value::Number(negate(num))),
+        kind => {
+            let zero = Expr {
+                kind: ExprKind::Literal(Value::Number(Number::Integer(0))),
+                span: Span::marker(span.start),
+            };
+            ExprKind::Binary(BinOp::Sub, Box::new(zero), Box::new(Expr { kind, span }))
+        }
+    };
+    Expr { kind, span }
+}
+
+pub(super) fn negate(num: Number) -> Number {
+    match num {
+        Number::Integer(i) => Number::Integer(-i),
+        Number::Float(f) => Number::Float(-f),
+    }
+}